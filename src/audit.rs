@@ -0,0 +1,387 @@
+//! An append-only log of every notification decision.
+//!
+//! Each notification the processor decides appends one JSON line to
+//! `$XDG_STATE_HOME/ghai/audit.jsonl` (falling back to `~/.local/state`): what was decided and
+//! what was actually done, the policies or rule behind it, the model's rationale, and the tokens it
+//! cost.  Entries are never rewritten, so the log answers "why was this marked read?" long after
+//! the decision cache has moved on.
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::config::state_home;
+use crate::error::Error;
+use crate::policy::{Decision, Explanation};
+use crate::types::{Notification, SubjectType};
+
+/////////////////////////////////////////// DecisionSource /////////////////////////////////////////
+
+string_enum! {
+    /// What reached a decision
+    pub enum DecisionSource {
+        /// The model, applying the policies
+        Model => "model",
+        /// A deterministic `when` rule
+        Rule => "rule",
+        /// The decision cache, holding an earlier model decision
+        Cache => "cache",
+        /// No rule matched and the token or cost budget was spent, so the model was not asked
+        Budget => "budget",
+    }
+}
+
+///////////////////////////////////////////// TokenUsage ///////////////////////////////////////////
+
+/// The tokens spent applying policies to one notification
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Requests made, counting retries after inconsistent output
+    pub iterations: usize,
+}
+
+impl From<&policyai::Usage> for TokenUsage {
+    fn from(usage: &policyai::Usage) -> Self {
+        let (input_tokens, output_tokens) = usage
+            .claudius_usage
+            .map(|u| (u.input_tokens.max(0) as u64, u.output_tokens.max(0) as u64))
+            .unwrap_or_default();
+        Self {
+            input_tokens,
+            output_tokens,
+            iterations: usage.iterations,
+        }
+    }
+}
+
+impl From<&claudius::Usage> for TokenUsage {
+    fn from(usage: &claudius::Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens.max(0) as u64,
+            output_tokens: usage.output_tokens.max(0) as u64,
+            iterations: 1,
+        }
+    }
+}
+
+impl TokenUsage {
+    /// Input and output tokens together
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.iterations += other.iterations;
+    }
+}
+
+///////////////////////////////////////////// AuditEntry ///////////////////////////////////////////
+
+/// One processed notification
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub thread_id: String,
+    pub repository: String,
+    pub subject_type: SubjectType,
+    pub subject: String,
+    pub decision: Decision,
+    /// The actions carried out, e.g. `mark-read` or `star`; empty when nothing was done
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub source: DecisionSource,
+    #[serde(flatten)]
+    pub explanation: Explanation,
+    /// The model that decided; `None` for rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// What deciding cost; `None` when no model was called
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+impl AuditEntry {
+    /// An entry for `thread`, stamped now, with no actions taken yet
+    pub fn new(
+        thread: &Notification,
+        decision: &Decision,
+        source: DecisionSource,
+        explanation: &Explanation,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            thread_id: thread.id.clone(),
+            repository: thread.repository.full_name.clone(),
+            subject_type: thread.subject.r#type.clone(),
+            subject: thread.subject.title.clone(),
+            decision: decision.clone(),
+            actions: Vec::new(),
+            dry_run: false,
+            source,
+            explanation: explanation.clone(),
+            model: None,
+            usage: None,
+        }
+    }
+}
+
+////////////////////////////////////////////// AuditLog ////////////////////////////////////////////
+
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// The log in its default location
+    pub fn open() -> Result<Self, Error> {
+        let dir = state_home().ok_or_else(|| {
+            Error::Config("cannot find a state directory; set XDG_STATE_HOME or HOME".to_string())
+        })?;
+        Ok(Self::at(dir.join("ghai").join("audit.jsonl")))
+    }
+
+    /// A log stored at `path`
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one entry
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(entry).expect("entries serialize");
+        line.push('\n');
+        // A single write of a whole line keeps concurrent appenders from interleaving.
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Every entry, oldest first; a missing log is empty
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, Error> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = Vec::new();
+        for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", self.path.display(), index + 1, e),
+                )
+            })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+///////////////////////////////////////////// AuditQuery //////////////////////////////////////////
+
+/// Which entries to select; every condition that is set must hold
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditQuery {
+    /// Entries at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Entries before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Entries for this repository (`owner/name`)
+    pub repo: Option<String>,
+    /// Entries whose decision or actions include this action, e.g. `mark-read` or `star`
+    pub action: Option<String>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self
+                .repo
+                .as_ref()
+                .is_none_or(|repo| entry.repository.eq_ignore_ascii_case(repo))
+            && self.action.as_ref().is_none_or(|action| {
+                entry.actions.contains(action)
+                    || entry
+                        .decision
+                        .action
+                        .as_ref()
+                        .is_some_and(|a| a.as_str() == action)
+            })
+    }
+}
+
+/// Parse a date (`YYYY-MM-DD`, meaning midnight UTC) or an RFC 3339 time
+///
+/// With `end_of_day`, a bare date means the following midnight, so that `--until 2024-05-01`
+/// includes all of that day.
+pub fn parse_time(input: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map_err(|_| format!("expected YYYY-MM-DD or an RFC 3339 time, got '{}'", input))?;
+    let date = if end_of_day {
+        date.succ_opt().unwrap_or(date)
+    } else {
+        date
+    };
+    Ok(date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::DecisionAction;
+
+    fn entry(repo: &str, action: DecisionAction, actions: &[&str], timestamp: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: timestamp.parse().unwrap(),
+            thread_id: "101".to_string(),
+            repository: repo.to_string(),
+            subject_type: SubjectType::PullRequest,
+            subject: "Add pagination to fetchers".to_string(),
+            decision: Decision {
+                action: Some(action),
+                priority: None,
+                label: vec![],
+                star_repo: false,
+                apply_label: false,
+            },
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+            dry_run: false,
+            source: DecisionSource::Model,
+            explanation: Explanation {
+                policies: vec!["If the pull request has no comments, output".to_string()],
+                rationale: Some("Nobody has commented yet.".to_string()),
+            },
+            model: Some("claude-sonnet-4-0".to_string()),
+            usage: Some(TokenUsage {
+                input_tokens: 1200,
+                output_tokens: 40,
+                iterations: 1,
+            }),
+        }
+    }
+
+    #[test]
+    fn log_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ghai-audit-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = AuditLog::at(dir.join("nested").join("audit.jsonl"));
+        assert!(log.entries().unwrap().is_empty());
+        let first = entry(
+            "rescrv/ghai",
+            DecisionAction::MarkRead,
+            &["mark-read"],
+            "2024-05-01T12:00:00Z",
+        );
+        let second = entry(
+            "rescrv/blue",
+            DecisionAction::MarkUnread,
+            &[],
+            "2024-05-02T12:00:00Z",
+        );
+        log.append(&first).unwrap();
+        log.append(&second).unwrap();
+        assert_eq!(vec![first, second], log.entries().unwrap());
+        let line = std::fs::read_to_string(log.path()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(line.lines().next().unwrap()).unwrap();
+        assert_eq!("Nobody has commented yet.", value["rationale"]);
+        assert_eq!(1200, value["usage"]["input_tokens"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn query_filters() {
+        let read = entry(
+            "rescrv/ghai",
+            DecisionAction::MarkRead,
+            &["mark-read", "star"],
+            "2024-05-01T12:00:00Z",
+        );
+        let unread = entry(
+            "rescrv/blue",
+            DecisionAction::MarkUnread,
+            &[],
+            "2024-05-02T12:00:00Z",
+        );
+        let query = |query: AuditQuery| (query.matches(&read), query.matches(&unread));
+        assert_eq!((true, true), query(AuditQuery::default()));
+        assert_eq!(
+            (false, true),
+            query(AuditQuery {
+                since: Some(parse_time("2024-05-02", false).unwrap()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            (true, false),
+            query(AuditQuery {
+                until: Some(parse_time("2024-05-01", true).unwrap()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            (false, true),
+            query(AuditQuery {
+                repo: Some("Rescrv/Blue".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            (true, false),
+            query(AuditQuery {
+                action: Some("star".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            (false, true),
+            query(AuditQuery {
+                action: Some("mark-unread".to_string()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_times() {
+        assert_eq!(
+            "2024-05-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+            parse_time("2024-05-01", false).unwrap()
+        );
+        assert_eq!(
+            "2024-05-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+            parse_time("2024-05-01", true).unwrap()
+        );
+        assert_eq!(
+            "2024-05-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+            parse_time("2024-05-01T12:00:00+02:00", true).unwrap()
+        );
+        assert!(parse_time("yesterday", false).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Discover a GitHub token the same places the `gh` CLI would.
+//!
+//! Sources are tried in order: `GITHUB_TOKEN`, `GH_TOKEN`, for hosts other than github.com
+//! `GITHUB_ENTERPRISE_TOKEN` and `GH_ENTERPRISE_TOKEN`, the `gh` CLI's `hosts.yml` for the
+//! configured host, and finally the `token_command` from the ghai config file.  A token stored in
+//! the system keyring by newer versions of `gh` can be reached with
+//! `"token_command": "gh auth token"`.
+
+use std::path::PathBuf;
+
+use crate::config::{config_home, Config};
+use crate::error::Error;
+
+/// Find a token for `host`, reporting every source that was tried if none has one
+pub fn discover_token(host: &str, config: &Config) -> Result<String, Error> {
+    resolve_token(|var| std::env::var(var).ok(), host, config)
+}
+
+/// Find a token for `host`, reading environment variables through `env`
+pub fn resolve_token(
+    env: impl Fn(&str) -> Option<String>,
+    host: &str,
+    config: &Config,
+) -> Result<String, Error> {
+    let mut tried = Vec::new();
+
+    for var in token_vars(host) {
+        match env(var).filter(|t| !t.trim().is_empty()) {
+            Some(token) => return Ok(token.trim().to_string()),
+            None => tried.push(format!("{var} environment variable: not set")),
+        }
+    }
+
+    match gh_hosts_path(&env) {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(content) => match hosts_yml_token(&content, host) {
+                Some(token) => return Ok(token),
+                None => tried.push(format!("{}: no oauth_token for {host}", path.display())),
+            },
+            Err(e) => tried.push(format!("{}: {e}", path.display())),
+        },
+        None => tried.push("gh hosts.yml: no config directory".to_string()),
+    }
+
+    match &config.token_command {
+        Some(command) => match run_token_command(command) {
+            Ok(token) => return Ok(token),
+            Err(e) => tried.push(format!("token_command `{command}`: {e}")),
+        },
+        None => tried.push("token_command in ghai config: not configured".to_string()),
+    }
+
+    Err(Error::NoToken(format!(
+        "no GitHub token found for {host}; tried:\n  - {}",
+        tried.join("\n  - ")
+    )))
+}
+
+/// The environment variables that may hold a token for `host`, in order
+///
+/// `GITHUB_TOKEN` and `GH_TOKEN` count for every host; the `gh` CLI's Enterprise variables follow
+/// them for hosts other than github.com and its `ghe.com` subdomains.
+fn token_vars(host: &str) -> Vec<&'static str> {
+    let mut vars = vec!["GITHUB_TOKEN", "GH_TOKEN"];
+    if host != "github.com" && !host.ends_with(".ghe.com") {
+        vars.extend(["GITHUB_ENTERPRISE_TOKEN", "GH_ENTERPRISE_TOKEN"]);
+    }
+    vars
+}
+
+/// The `gh` CLI's hosts file: `$GH_CONFIG_DIR/hosts.yml`, else `$XDG_CONFIG_HOME/gh/hosts.yml`
+fn gh_hosts_path(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    if let Some(dir) = env("GH_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("hosts.yml"));
+    }
+    config_home().map(|dir| dir.join("gh").join("hosts.yml"))
+}
+
+/// Pull the `oauth_token` for `host` out of the contents of a `gh` hosts.yml
+///
+/// The file is a map from hostname to per-host settings; only the host's own top-level
+/// `oauth_token` is considered, not those of the nested per-user entries.
+pub fn hosts_yml_token(content: &str, host: &str) -> Option<String> {
+    let mut in_host = false;
+    let mut host_indent = None;
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 {
+            in_host = unquote(line.trim_end().trim_end_matches(':')) == host;
+            host_indent = None;
+            continue;
+        }
+        if !in_host {
+            continue;
+        }
+        let indent_of_host_keys = *host_indent.get_or_insert(indent);
+        if indent != indent_of_host_keys {
+            continue;
+        }
+        if let Some((key, value)) = line.trim().split_once(':') {
+            if key.trim() == "oauth_token" {
+                let token = unquote(value.trim());
+                if !token.is_empty() {
+                    return Some(token.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(s)
+}
+
+fn run_token_command(command: &str) -> Result<String, String> {
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        return Err("printed no token".to_string());
+    }
+    Ok(token)
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS_YML: &str = r#"github.com:
+    users:
+        alice:
+            oauth_token: gho_nested
+    git_protocol: https
+    oauth_token: gho_public
+    user: alice
+"github.example.com":
+    oauth_token: "gho_enterprise"
+"#;
+
+    #[test]
+    fn hosts_yml_token_per_host() {
+        assert_eq!(
+            hosts_yml_token(HOSTS_YML, "github.com").as_deref(),
+            Some("gho_public")
+        );
+        assert_eq!(
+            hosts_yml_token(HOSTS_YML, "github.example.com").as_deref(),
+            Some("gho_enterprise")
+        );
+        assert_eq!(hosts_yml_token(HOSTS_YML, "other.example.com"), None);
+    }
+
+    #[test]
+    fn hosts_yml_keyring_entry_has_no_token() {
+        let content =
+            "github.com:\n    git_protocol: ssh\n    users:\n        alice:\n    user: alice\n";
+        assert_eq!(hosts_yml_token(content, "github.com"), None);
+    }
+
+    #[test]
+    fn github_token_wins() {
+        let env = |var: &str| match var {
+            "GITHUB_TOKEN" => Some("first".to_string()),
+            "GH_TOKEN" => Some("second".to_string()),
+            _ => None,
+        };
+        let token = resolve_token(env, "github.com", &Config::default()).unwrap();
+        assert_eq!(token, "first");
+    }
+
+    #[test]
+    fn gh_token_is_second() {
+        let env = |var: &str| (var == "GH_TOKEN").then(|| "second".to_string());
+        let token = resolve_token(env, "github.com", &Config::default()).unwrap();
+        assert_eq!(token, "second");
+    }
+
+    #[test]
+    fn enterprise_tokens_follow_for_other_hosts() {
+        let env = |var: &str| match var {
+            "GITHUB_TOKEN" => Some("first".to_string()),
+            "GH_ENTERPRISE_TOKEN" => Some("enterprise".to_string()),
+            _ => None,
+        };
+        let token = resolve_token(env, "github.example.com", &Config::default()).unwrap();
+        assert_eq!(token, "first");
+        let env = |var: &str| match var {
+            "GH_ENTERPRISE_TOKEN" => Some("enterprise".to_string()),
+            "GH_CONFIG_DIR" => Some("/nonexistent".to_string()),
+            _ => None,
+        };
+        let token = resolve_token(env, "github.example.com", &Config::default()).unwrap();
+        assert_eq!(token, "enterprise");
+        let err = resolve_token(env, "github.com", &Config::default())
+            .unwrap_err()
+            .to_string();
+        assert!(!err.contains("ENTERPRISE"), "{err}");
+    }
+
+    #[test]
+    fn token_command_is_last() {
+        let config = Config {
+            token_command: Some("echo from-command".to_string()),
+            ..Config::default()
+        };
+        let env = |var: &str| (var == "GH_CONFIG_DIR").then(|| "/nonexistent".to_string());
+        let token = resolve_token(env, "github.com", &config).unwrap();
+        assert_eq!(token, "from-command");
+    }
+
+    #[test]
+    fn error_lists_every_source() {
+        let env = |var: &str| (var == "GH_CONFIG_DIR").then(|| "/nonexistent".to_string());
+        let err = resolve_token(env, "github.com", &Config::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("GITHUB_TOKEN"), "{err}");
+        assert!(err.contains("GH_TOKEN"), "{err}");
+        assert!(err.contains("/nonexistent/hosts.yml"), "{err}");
+        assert!(err.contains("token_command"), "{err}");
+    }
+}
@@ -0,0 +1,119 @@
+//! Query the audit log that `ghai-process-notifications` keeps of every decision.
+//!
+//! Each entry says what was decided for a notification, what was actually done, which policies
+//! or rule decided it, the model's rationale, and the tokens it cost.  Filters combine; with none,
+//! every entry is printed, oldest first.
+
+use arrrg::CommandLine;
+
+use ghai::audit::{parse_time, AuditEntry, AuditLog, AuditQuery};
+
+#[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
+struct Options {
+    #[arrrg(
+        optional,
+        "Only entries at or after this date (YYYY-MM-DD) or time (RFC 3339)"
+    )]
+    since: Option<String>,
+    #[arrrg(
+        optional,
+        "Only entries up to and including this date (YYYY-MM-DD) or before this time (RFC 3339)"
+    )]
+    until: Option<String>,
+    #[arrrg(optional, "Only entries for this repository (owner/name)")]
+    repo: Option<String>,
+    #[arrrg(
+        optional,
+        "Only entries that decided or took this action (e.g. mark-read, star)"
+    )]
+    action: Option<String>,
+    #[arrrg(flag, "Print matching entries as JSON lines")]
+    json: bool,
+}
+
+fn print_entry(entry: &AuditEntry) {
+    let decided = entry
+        .decision
+        .action
+        .as_ref()
+        .map(|action| action.as_str())
+        .unwrap_or("no action");
+    let done = if entry.dry_run {
+        "dry run".to_string()
+    } else if entry.actions.is_empty() {
+        "nothing done".to_string()
+    } else {
+        entry.actions.join(", ")
+    };
+    println!(
+        "{}  {}  {} ({})  {}",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.repository,
+        decided,
+        done,
+        entry.subject
+    );
+    println!("    decided by: {}", entry.source);
+    for policy in &entry.explanation.policies {
+        println!("    matched: {}", policy);
+    }
+    if let Some(rationale) = &entry.explanation.rationale {
+        println!("    rationale: {}", rationale);
+    }
+    if let Some(model) = &entry.model {
+        match &entry.usage {
+            Some(usage) => println!(
+                "    model: {} ({} input, {} output tokens)",
+                model, usage.input_tokens, usage.output_tokens
+            ),
+            None => println!("    model: {}", model),
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (opts, free) = Options::from_command_line_relaxed("USAGE: ghai-audit [options]");
+    if !free.is_empty() {
+        eprintln!("command takes no arguments");
+        std::process::exit(1);
+    }
+
+    let time = |input: &Option<String>, end_of_day: bool| match input {
+        Some(input) => match parse_time(input, end_of_day) {
+            Ok(time) => Some(time),
+            Err(e) => {
+                eprintln!("❌ Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let query = AuditQuery {
+        since: time(&opts.since, false),
+        until: time(&opts.until, true),
+        repo: opts.repo.clone(),
+        action: opts.action.clone(),
+    };
+
+    let log = AuditLog::open()?;
+    let entries = log
+        .entries()?
+        .into_iter()
+        .filter(|entry| query.matches(entry))
+        .collect::<Vec<_>>();
+    if opts.json {
+        for entry in &entries {
+            println!("{}", serde_json::to_string(entry)?);
+        }
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("No matching entries in {}", log.path().display());
+        return Ok(());
+    }
+    for entry in &entries {
+        print_entry(entry);
+    }
+    println!("\n{} entries", entries.len());
+    Ok(())
+}
@@ -2,7 +2,8 @@ use std::collections::HashSet;
 
 use arrrg::CommandLine;
 
-use ghai::Action;
+use ghai::http::GitHubClient;
+use ghai::{Action, RunStatus};
 
 #[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
 struct Options {
@@ -16,36 +17,43 @@ struct Options {
     status: Option<String>,
 }
 
+/// How many of the most recent workflow runs each poll looks at
+const RECENT_RUNS: usize = 100;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (options, free) =
         Options::from_command_line_relaxed("USAGE: ghai-notify-finished-action [options]");
+    let client = GitHubClient::new()?;
     let mut first = true;
     let mut in_progress = HashSet::new();
     loop {
         let actions = Action::fetch_all(
+            &client,
             options.owner.clone(),
             options.repo.clone(),
             options.actor.clone(),
             None,
             None,
             options.status.clone(),
+            Some(RECENT_RUNS as u64),
             None,
-            None,
+            Some(RECENT_RUNS),
         )
         .await?;
+        for warning in client.take_warnings() {
+            eprintln!("⚠ {}", warning);
+        }
         if first {
             for action in actions {
-                if action.status != Some("completed".to_string()) {
+                if action.status != Some(RunStatus::Completed) {
                     in_progress.insert(action.id);
                 }
             }
             first = false;
         } else {
             for action in actions {
-                if action.status == Some("completed".to_string())
-                    && in_progress.contains(&action.id)
-                {
+                if action.status == Some(RunStatus::Completed) && in_progress.contains(&action.id) {
                     in_progress.remove(&action.id);
                 }
             }
@@ -0,0 +1,187 @@
+//! Check policy files against saved notifications and the decisions expected for them.
+//!
+//! A case directory holds pairs of files: `NAME.xml`, a notification context as built for
+//! `ghai-process-notifications` (the decision cache keeps one for every thread it has decided), and
+//! `NAME.json`, the decision the policies should reach for it.  Every case is run and every
+//! difference reported; the exit status is non-zero if any case fails.
+
+use std::path::{Path, PathBuf};
+
+use arrrg::CommandLine;
+use claudius::MessageCreateParams;
+use policyai::{Manager, Policy};
+
+use ghai::config::Config;
+use ghai::llm::{apply_policies, KeywordLlm, Llm, LlmOptions};
+use ghai::policy::{parse_policies, Decision};
+
+#[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
+struct Options {
+    #[arrrg(
+        required,
+        "Directory of saved contexts (NAME.xml) and expected decisions (NAME.json)"
+    )]
+    cases: String,
+    #[arrrg(
+        flag,
+        "Decide with a keyword-matching fake instead of a language model"
+    )]
+    fake_llm: bool,
+    #[arrrg(flag, "Only report failures")]
+    quiet: bool,
+    #[arrrg(nested)]
+    llm: LlmOptions,
+}
+
+/// The model used when neither the command line nor the config file chooses one
+const DEFAULT_MODEL: &str = "claude-sonnet-4-0";
+
+/// A saved context and the decision expected for it
+struct Case {
+    name: String,
+    context: PathBuf,
+    expected: PathBuf,
+}
+
+/// Every `NAME.xml` in `dir`, in name order
+fn find_cases(dir: &Path) -> Result<Vec<Case>, ghai::Error> {
+    let mut cases = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "xml") {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            cases.push(Case {
+                name,
+                expected: path.with_extension("json"),
+                context: path,
+            });
+        }
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// The fields on which `actual` differs from `expected`, one line each
+fn diff(expected: &Decision, actual: &Decision) -> Vec<String> {
+    let expected = serde_json::to_value(expected).expect("decisions serialize");
+    let actual = serde_json::to_value(actual).expect("decisions serialize");
+    let (Some(expected), Some(actual)) = (expected.as_object(), actual.as_object()) else {
+        return vec![];
+    };
+    let null = serde_json::Value::Null;
+    expected
+        .iter()
+        .filter_map(|(field, want)| {
+            let got = actual.get(field).unwrap_or(&null);
+            (want != got).then(|| format!("{}: expected {}, got {}", field, want, got))
+        })
+        .collect()
+}
+
+/// Run one case, returning the differences from the expected decision
+async fn run_case(
+    llm: &dyn Llm,
+    policies: &[Policy],
+    case: &Case,
+) -> Result<Vec<String>, ghai::Error> {
+    let context = std::fs::read_to_string(&case.context)?;
+    let expected = std::fs::read_to_string(&case.expected)?;
+    let expected =
+        Decision::from_value(serde_json::from_str(&expected).map_err(std::io::Error::from)?)?;
+    let mut manager = Manager::default();
+    for policy in policies {
+        manager.add(policy.clone());
+    }
+    let template = MessageCreateParams {
+        max_tokens: 3333,
+        messages: vec![],
+        ..Default::default()
+    };
+    let report = apply_policies(llm, &mut manager, template, &context, None).await?;
+    let actual = Decision::from_value(report.value())?;
+    Ok(diff(&expected, &actual))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (opts, args) = Options::from_command_line_relaxed(
+        "USAGE: ghai-policy-test --cases DIR [options] policy-files...",
+    );
+    if args.is_empty() {
+        eprintln!("❌ Error: no policy files given");
+        std::process::exit(1);
+    }
+
+    let mut content = String::new();
+    for file_path in &args {
+        match std::fs::read_to_string(file_path) {
+            Ok(file) => {
+                content.push_str(&file);
+                content.push('\n');
+            }
+            Err(e) => {
+                eprintln!("❌ Error reading policy file '{}': {}", file_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let policies = match parse_policies(&content) {
+        Ok(set) => {
+            if !set.rules.is_empty() && !opts.quiet {
+                println!(
+                    "⚠ Skipping {} `when` rules: saved contexts only exercise model policies\n",
+                    set.rules.len()
+                );
+            }
+            set.policies
+        }
+        Err(e) => {
+            eprintln!("❌ Error in policy files: {}", e);
+            std::process::exit(13);
+        }
+    };
+    let llm: Box<dyn Llm> = if opts.fake_llm {
+        Box::new(KeywordLlm::new(&policies)?)
+    } else {
+        let config = Config::load()?;
+        opts.llm
+            .connect(&config.llm, "ghai-policy-test", DEFAULT_MODEL)?
+    };
+
+    let cases = find_cases(Path::new(&opts.cases))?;
+    let mut failed = 0;
+    for case in &cases {
+        match run_case(llm.as_ref(), &policies, case).await {
+            Ok(differences) if differences.is_empty() => {
+                if !opts.quiet {
+                    println!("✓ {}", case.name);
+                }
+            }
+            Ok(differences) => {
+                failed += 1;
+                println!("✗ {}", case.name);
+                for difference in differences {
+                    println!("    {}", difference);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("✗ {}", case.name);
+                println!("    error: {}", e);
+            }
+        }
+    }
+    println!(
+        "\n{} cases: {} passed, {} failed",
+        cases.len(),
+        cases.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use arrrg::CommandLine;
 
+use ghai::http::GitHubClient;
 use ghai::Action;
 
 #[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
@@ -14,6 +15,9 @@ struct Options {
     status: Option<String>,
 }
 
+/// How many of the most recent workflow runs to print
+const RECENT_RUNS: usize = 100;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (options, free) =
@@ -22,19 +26,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         eprintln!("command takes no arguments");
         std::process::exit(1);
     }
-    for action in Action::fetch_all(
+    let client = GitHubClient::new()?;
+    let actions = Action::fetch_all(
+        &client,
         options.owner.clone(),
         options.repo.clone(),
         options.actor.clone(),
         None,
         None,
         options.status.clone(),
+        Some(RECENT_RUNS as u64),
         None,
-        None,
+        Some(RECENT_RUNS),
     )
-    .await?
-    .into_iter()
-    {
+    .await?;
+    for warning in client.take_warnings() {
+        eprintln!("⚠ {}", warning);
+    }
+    for action in actions {
         println!("{}", action.jobs_url);
     }
     Ok(())
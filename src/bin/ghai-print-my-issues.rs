@@ -1,5 +1,6 @@
 use arrrg::CommandLine;
 use chrono::DateTime;
+use ghai::http::GitHubClient;
 use ghai::Issue;
 
 #[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
@@ -21,8 +22,10 @@ struct Options {
     since: Option<String>,
     #[arrrg(optional, "Number of results per page (default 30, max 100)")]
     per_page: Option<u64>,
-    #[arrrg(optional, "Page number")]
+    #[arrrg(optional, "Page number to start from")]
     page: Option<u64>,
+    #[arrrg(optional, "Maximum number of issues to show (default all)")]
+    limit: Option<usize>,
     #[arrrg(flag, "Show only issue URLs")]
     urls_only: bool,
     #[arrrg(flag, "Show detailed information")]
@@ -45,7 +48,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None
     };
 
+    let client = GitHubClient::new()?;
     let issues = Issue::fetch_user_issues(
+        &client,
         options.filter,
         options.state,
         options.labels,
@@ -54,8 +59,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         since,
         options.per_page,
         options.page,
+        options.limit,
     )
     .await?;
+    for warning in client.take_warnings() {
+        eprintln!("⚠ {}", warning);
+    }
 
     if issues.is_empty() {
         println!("No issues found");
@@ -4,14 +4,33 @@
 //! subscribed to types of activity.
 
 use arrrg::CommandLine;
-use claudius::{Anthropic, ContentBlock, KnownModel, MessageCreateParams, Model};
+use chrono::{DateTime, Utc};
+use claudius::MessageCreateParams;
 use policyai::{Manager, Policy, Usage};
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinHandle;
 
-use chrono::{DateTime, FixedOffset};
-use ghai::parser::parse_lines;
-use ghai::policy::{get_policy_type, Decision};
-use ghai::xml::{build_issue_notification_context, build_pull_request_notification_context};
+use ghai::audit::{AuditEntry, AuditLog, DecisionSource, TokenUsage};
+use ghai::budget::{Budget, Cost, ModelPrice, Spend};
+use ghai::cache::{fingerprint, CachedDecision, DecisionCache};
+use ghai::config::Config;
+use ghai::http::GitHubClient;
+use ghai::journal::{Change, Journal, Run};
+use ghai::llm::{apply_policies, explain, text, Llm, LlmOptions};
+use ghai::policy::{parse_policies, Decision, DecisionAction, Explanation};
+use ghai::queue::{QueueEntry, QueueFormat, ReviewQueue};
+use ghai::rules::{first_match, Rule};
+use ghai::subject::Subject;
+use ghai::xml::{
+    build_commit_notification_context, build_discussion_notification_context,
+    build_issue_notification_context, build_pull_request_notification_context,
+    build_release_notification_context, build_security_alert_notification_context,
+    build_workflow_run_notification_context, DEFAULT_CONTEXT_TOKENS,
+};
 use ghai::{CommentFetcher, IssueComment, Notification};
 
 #[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
@@ -28,45 +47,114 @@ struct Options {
     no_confirm: bool,
     #[arrrg(flag, "Mark as read by default; otherwise do nothing")]
     mark_read_by_default: bool,
+    #[arrrg(
+        flag,
+        "Decide every notification afresh without reading or updating the cache"
+    )]
+    no_cache: bool,
+    #[arrrg(
+        flag,
+        "Keep polling for new notifications until interrupted (requires --no-confirm or --dry-run)"
+    )]
+    watch: bool,
+    #[arrrg(
+        optional,
+        "Seconds between polls in --watch mode; never shorter than GitHub's X-Poll-Interval"
+    )]
+    poll_interval: Option<u64>,
+    #[arrrg(optional, "Review queue format: text, markdown, or json")]
+    queue_format: Option<String>,
+    #[arrrg(
+        optional,
+        "Write the review queue to this file instead of standard output"
+    )]
+    queue_file: Option<String>,
+    #[arrrg(
+        flag,
+        "Undo the changes of the last run, or of the run id given in place of policy files"
+    )]
+    undo: bool,
+    #[arrrg(
+        optional,
+        "Decide up to this many notifications at once (default 1); output and prompts stay in order"
+    )]
+    jobs: Option<u64>,
+    #[arrrg(
+        optional,
+        "Stop calling the model once this many input and output tokens have been spent"
+    )]
+    max_tokens_budget: Option<u64>,
+    #[arrrg(
+        optional,
+        "Stop calling the model once its estimated cost reaches this many dollars"
+    )]
+    max_cost: Option<Cost>,
+    #[arrrg(
+        flag,
+        "Tell the model which files each pull request changes, with their additions and deletions"
+    )]
+    changed_files: bool,
+    #[arrrg(
+        optional,
+        "Also show pull request patches, up to this many characters in all (implies --changed-files)"
+    )]
+    patch_budget: Option<u64>,
+    #[arrrg(
+        optional,
+        "Fit each notification's context into about this many tokens (default 8000)"
+    )]
+    context_tokens: Option<u64>,
+    #[arrrg(nested)]
+    llm: LlmOptions,
 }
 
+/// The model used when neither the command line nor the config file chooses one
+const DEFAULT_MODEL: &str = "claude-sonnet-4-0";
+/// How long to wait between polls when GitHub does not send `X-Poll-Interval`
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
 async fn generate_summary(
-    client: &Anthropic,
+    llm: &dyn Llm,
     xml_context: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
+) -> Result<(String, TokenUsage), ghai::Error> {
     let prompt = format!(
         "Please provide a single-line summary of this GitHub notification. Be concise and descriptive, focusing on what the PR/Issue is about:\n\n{}", 
         xml_context
     );
     let req = MessageCreateParams {
         max_tokens: 128,
-        model: Model::Known(KnownModel::ClaudeSonnet40),
         messages: vec![prompt.into()],
         ..Default::default()
     };
-    let resp = client.send(req).await?;
-    Ok(resp
-        .content
-        .iter()
-        .filter_map(|x| {
-            if let ContentBlock::Text(t) = x {
-                Some(t.text.clone())
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n"))
+    let message = llm.send(req).await?;
+    Ok((text(&message), TokenUsage::from(&message.usage)))
 }
 
-fn format_decision(decision: &Decision, opts: &Options) -> String {
+fn format_decision(decided: &Decided, opts: &Options) -> String {
     if opts.json {
-        serde_json::to_string_pretty(decision).unwrap_or_else(|_| "{\"error\": true}".to_string())
+        #[derive(serde::Serialize)]
+        struct Shown<'a> {
+            #[serde(flatten)]
+            decision: &'a Decision,
+            #[serde(flatten)]
+            explanation: &'a Explanation,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            usage: Option<TokenUsage>,
+        }
+        let shown = Shown {
+            decision: &decided.decision,
+            explanation: &decided.explanation,
+            usage: decided.usage,
+        };
+        serde_json::to_string_pretty(&shown).unwrap_or_else(|_| "{\"error\": true}".to_string())
     } else {
-        match decision.action.as_str() {
-            "mark-read" => "✓ Mark as READ".to_string(),
-            "mark-unread" => "📌 Keep as unread".to_string(),
-            "" if opts.mark_read_by_default => "📖 Default to read".to_string(),
+        match &decided.decision.action {
+            Some(DecisionAction::Ignore) => "🔇 IGNORE thread".to_string(),
+            Some(DecisionAction::Unsubscribe) => "🔕 UNSUBSCRIBE from thread".to_string(),
+            Some(DecisionAction::MarkDone) => "✅ Mark as DONE".to_string(),
+            Some(DecisionAction::MarkRead) => "✓ Mark as READ".to_string(),
+            Some(DecisionAction::MarkUnread) => "📌 Keep as unread".to_string(),
+            None if opts.mark_read_by_default => "📖 Default to read".to_string(),
             _ => "⏭  Skip (no action)".to_string(),
         }
     }
@@ -91,7 +179,7 @@ fn display_notification_info(thread: &Notification, opts: &Options) {
         if let Some(last_read) = &thread.last_read_at {
             println!("   Last read: {}", last_read);
         }
-        println!("   URL: {}", thread.subject.url);
+        println!("   URL: {}", thread.html_url());
         println!("   ---");
     } else {
         println!(
@@ -103,7 +191,7 @@ fn display_notification_info(thread: &Notification, opts: &Options) {
     }
 }
 
-fn confirm_via_ui(summary: &str, action: &str, opts: &Options) -> Result<bool, ()> {
+fn confirm_via_ui(summary: &str, plan: &str, opts: &Options) -> Result<bool, ()> {
     if opts.no_confirm {
         return Ok(true);
     }
@@ -111,7 +199,12 @@ fn confirm_via_ui(summary: &str, action: &str, opts: &Options) -> Result<bool, (
     if !opts.quiet {
         println!("Summary: {}", summary);
     }
-    print!("Mark as {}? (y/N/q to quit): ", action);
+    let mut chars = plan.chars();
+    let plan = chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default();
+    print!("{}? (y/N/q to quit): ", plan);
     io::stdout().flush().unwrap();
 
     let mut input = String::new();
@@ -124,226 +217,1218 @@ fn confirm_via_ui(summary: &str, action: &str, opts: &Options) -> Result<bool, (
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let (opts, args) = Options::from_command_line_relaxed(
-        "USAGE: ghai-process-notifications [options] [policy-files...]",
-    );
+/// One GitHub call a decision asks for
+#[derive(Debug)]
+enum Step {
+    Thread(DecisionAction),
+    Star,
+    Label(Vec<String>),
+}
 
-    if opts.json && !opts.dry_run {
-        eprintln!("❌ Error: --json flag requires --dry-run flag");
-        eprintln!("   JSON output is only supported in dry-run mode to preview decisions");
-        std::process::exit(1);
+impl Step {
+    /// The steps that carry out `decision`, in the order they run
+    fn plan(decision: &Decision, opts: &Options) -> Vec<Step> {
+        let mut steps = Vec::new();
+        match &decision.action {
+            Some(DecisionAction::MarkUnread) | Some(DecisionAction::Unknown(_)) => {}
+            Some(action) => steps.push(Step::Thread(action.clone())),
+            None if opts.mark_read_by_default => {
+                steps.push(Step::Thread(DecisionAction::MarkRead));
+            }
+            None => {}
+        }
+        if decision.star_repo {
+            steps.push(Step::Star);
+        }
+        if decision.apply_label && !decision.label.is_empty() {
+            steps.push(Step::Label(decision.label.clone()));
+        }
+        steps
     }
 
-    let client = Anthropic::new(None)?;
-    let mut manager = Manager::default();
+    /// The action the audit log records when this step succeeds
+    fn name(&self) -> &str {
+        match self {
+            Step::Thread(action) => action.as_str(),
+            Step::Star => "star",
+            Step::Label(_) => "label",
+        }
+    }
 
-    // Read and concatenate all policy files
-    let mut concatenated_content = String::new();
-    for file_path in &args {
-        match std::fs::read_to_string(file_path) {
-            Ok(content) => {
-                if !opts.quiet {
-                    println!("📂 Loaded policy file: {}", file_path);
-                }
-                concatenated_content.push_str(&content);
-                concatenated_content.push('\n');
-            }
-            Err(e) => {
-                eprintln!("❌ Error reading policy file '{}': {}", file_path, e);
-                eprintln!("   Please check that the file exists and is readable.");
-                std::process::exit(1);
-            }
+    fn describe(&self, thread: &Notification) -> String {
+        match self {
+            Step::Thread(DecisionAction::Ignore) => "ignore the thread".to_string(),
+            Step::Thread(DecisionAction::Unsubscribe) => "unsubscribe".to_string(),
+            Step::Thread(DecisionAction::MarkDone) => "mark as done".to_string(),
+            Step::Thread(_) => "mark as read".to_string(),
+            Step::Star => format!("star {}", thread.repository.full_name),
+            Step::Label(labels) => format!("label it {}", labels.join(", ")),
         }
     }
 
-    // Parse the concatenated content using the parser
-    if !concatenated_content.trim().is_empty() {
-        let parsed_results = parse_lines(&concatenated_content);
-        for result in parsed_results {
-            match result {
-                Ok((prompt, action_json)) => {
-                    // Validate action_json decodes to a Decision
-                    match serde_json::from_value::<Decision>(action_json.clone()) {
-                        Ok(_) => {
-                            let policy = Policy {
-                                r#type: get_policy_type(),
-                                prompt,
-                                action: action_json,
-                            };
-                            manager.add(policy);
-                        }
-                        Err(e) => {
-                            eprintln!("❌ Error validating policy decision:");
-                            eprintln!("   Prompt: {}", prompt);
-                            eprintln!("   Error: {}", e);
-                            eprintln!("   Please check the decision format in your policy file.");
-                            std::process::exit(13);
-                        }
-                    }
+    /// Carry out the step, returning what to report and the change made, if any
+    async fn run(
+        &self,
+        github: &GitHubClient,
+        thread: &Notification,
+        tally: &mut Tally,
+    ) -> Result<(String, Option<Change>), ghai::Error> {
+        match self {
+            Step::Thread(DecisionAction::Ignore) => {
+                thread.ignore(github).await?;
+                thread.mark_as_read(github).await?;
+                tally.ignored += 1;
+                Ok(("🔇 Ignored thread".to_string(), Some(Change::Ignore)))
+            }
+            Step::Thread(DecisionAction::Unsubscribe) => {
+                thread.unsubscribe(github).await?;
+                thread.mark_as_read(github).await?;
+                tally.unsubscribed += 1;
+                Ok(("🔕 Unsubscribed".to_string(), Some(Change::Unsubscribe)))
+            }
+            Step::Thread(DecisionAction::MarkDone) => {
+                thread.mark_as_done(github).await?;
+                tally.marked_done += 1;
+                Ok(("✅ Marked as done".to_string(), Some(Change::MarkDone)))
+            }
+            Step::Thread(_) => {
+                thread.mark_as_read(github).await?;
+                tally.marked_read += 1;
+                Ok(("✓ Marked as read".to_string(), Some(Change::MarkRead)))
+            }
+            // Stars and labels that were already there are left alone, so undoing the run never
+            // removes something it did not add.
+            Step::Star => {
+                let repo = &thread.repository.full_name;
+                if thread.is_repository_starred(github).await? {
+                    return Ok((format!("⭐ {} is already starred", repo), None));
                 }
-                Err(e) => {
-                    eprintln!("❌ Error parsing policy file:");
-                    eprintln!("   Error: {}", e);
-                    eprintln!("   Please check the syntax of your policy file.");
-                    std::process::exit(13);
+                thread.star_repository(github).await?;
+                tally.starred += 1;
+                Ok((format!("⭐ Starred {}", repo), Some(Change::Star)))
+            }
+            Step::Label(labels) => {
+                let existing = thread.fetch_labels(github).await?;
+                let labels = labels
+                    .iter()
+                    .filter(|label| {
+                        !existing
+                            .iter()
+                            .any(|e| e.name().eq_ignore_ascii_case(label))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if labels.is_empty() {
+                    return Ok(("🏷  Already labeled".to_string(), None));
                 }
+                thread.add_labels(github, &labels).await?;
+                tally.labeled += 1;
+                Ok((
+                    format!("🏷  Labeled {}", labels.join(", ")),
+                    Some(Change::Label { labels }),
+                ))
             }
         }
     }
-    let notifications =
-        Notification::fetch_all::<chrono::FixedOffset>(false, false, None, None).await?;
+}
 
-    let total_notifications = notifications.len();
+/// Running totals for the final report
+#[derive(Debug, Default)]
+struct Tally {
+    processed: usize,
+    marked_read: usize,
+    marked_unread: usize,
+    marked_done: usize,
+    unsubscribed: usize,
+    ignored: usize,
+    starred: usize,
+    labeled: usize,
+    skipped: usize,
+    cache_hits: usize,
+    rule_decisions: usize,
+    /// Notifications left to `--mark-read-by-default` because the budget was spent
+    over_budget: usize,
+}
 
-    if !opts.quiet {
-        println!("\n📊 Processing {} notifications...\n", total_notifications);
+/// A decision and how it was reached
+struct Decided {
+    decision: Decision,
+    summary: String,
+    source: DecisionSource,
+    explanation: Explanation,
+    usage: Option<TokenUsage>,
+    /// Problems that did not stop the decision, reported when it is processed
+    warnings: Vec<String>,
+}
+
+/// What deciding a notification came to
+#[allow(clippy::large_enum_variant)]
+enum Outcome {
+    Decided(Decided),
+    /// ghai does not support the notification's subject type
+    Unsupported,
+    /// The subject could not be found or may not be seen, and no rule decided without it
+    Unavailable(ghai::Error),
+}
+
+/// Whether to keep going after a notification
+#[derive(Debug, Eq, PartialEq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Everything needed to decide a notification, shared by the decisions `--jobs` runs at once
+///
+/// Deciding prints nothing.  Its output belongs to the processing that follows, which is serialized
+/// so that output and prompts appear in the order the notifications arrived.
+struct Decider {
+    github: GitHubClient,
+    llm: Box<dyn Llm>,
+    rules: Vec<Rule>,
+    policies: Vec<Policy>,
+    /// The prompts of the policies, in order, for explaining reports
+    prompts: Vec<String>,
+    cache: Option<DecisionCache>,
+    fingerprint: String,
+    /// Whether pull request contexts list changed files
+    changed_files: bool,
+    /// Characters of patches pull request contexts may show
+    patch_budget: usize,
+    /// Tokens each notification context may take
+    context_tokens: usize,
+    budget: Budget,
+    /// What the model calls have cost so far, across every job
+    spend: Mutex<Spend>,
+}
+
+impl Decider {
+    /// Why the model must not be called again, if the budget is spent
+    fn over_budget(&self) -> Option<String> {
+        self.budget.exhausted(&self.spend.lock().unwrap())
     }
 
-    let mut processed = 0;
-    let mut marked_read = 0;
-    let mut marked_unread = 0;
-    let mut skipped = 0;
+    fn record_usage(&self, usage: TokenUsage) {
+        self.spend.lock().unwrap().add(self.llm.model(), usage);
+    }
 
-    for thread in notifications.into_iter() {
-        processed += 1;
+    /// The decision for `thread` and how it was reached, unless its subject is unsupported or
+    /// unavailable
+    async fn decide(&self, thread: &Notification) -> Result<Outcome, ghai::Error> {
+        if let Some(cached) = self
+            .cache
+            .as_ref()
+            .and_then(|c| c.get(thread, &self.fingerprint))
+        {
+            return Ok(Outcome::Decided(Decided {
+                decision: cached.decision,
+                summary: cached.summary,
+                source: DecisionSource::Cache,
+                explanation: cached.explanation,
+                usage: None,
+                warnings: Vec::new(),
+            }));
+        }
 
-        if !opts.quiet && !opts.json {
-            println!("🔄 Processing {}/{}", processed, total_notifications);
+        let github = &self.github;
+        // Discussions, workflow runs, and alerts are found by guessing, and alerts need admin
+        // rights, so a subject that cannot be had leaves the thread to the rules alone.
+        let (subject, unavailable) = match Subject::fetch(thread, github).await {
+            Ok(subject) => (subject, None),
+            Err(
+                e @ (ghai::Error::MissingSubject(_)
+                | ghai::Error::NotFound(_)
+                | ghai::Error::Forbidden(_)),
+            ) => (None, Some(e)),
+            Err(e) => return Err(e),
+        };
+        if let Some(rule) = first_match(&self.rules, thread, subject.as_ref()) {
+            return Ok(Outcome::Decided(Decided {
+                decision: rule.decision.clone(),
+                summary: thread.subject.title.clone(),
+                source: DecisionSource::Rule,
+                explanation: Explanation {
+                    policies: vec![rule.source.clone()],
+                    rationale: None,
+                },
+                usage: None,
+                warnings: unavailable
+                    .iter()
+                    .map(|e| format!("Could not fetch the subject: {}", e))
+                    .collect(),
+            }));
         }
-        let llm_prompt = match thread.subject.r#type.as_str() {
-            "PullRequest" => {
-                let pr = thread.fetch_pull_request().await?;
-                std::thread::sleep(std::time::Duration::from_secs(1));
+        // Calls already in flight when the budget runs out may overspend it slightly.
+        if let Some(reason) = self.over_budget() {
+            return Ok(Outcome::Decided(Decided {
+                decision: Decision::default(),
+                summary: thread.subject.title.clone(),
+                source: DecisionSource::Budget,
+                explanation: Explanation {
+                    policies: vec![],
+                    rationale: Some(format!("Not sent to the model: {}", reason)),
+                },
+                usage: None,
+                warnings: Vec::new(),
+            }));
+        }
+        let mut warnings = Vec::new();
+        let llm_prompt = match &subject {
+            Some(Subject::PullRequest(pr)) => {
                 let comments_since_last_read =
-                    fetch_comments_since_last_read(&pr, &thread, &opts).await;
-                build_pull_request_notification_context(&thread, &pr, &comments_since_last_read)
+                    fetch_comments_since_last_read(github, pr, thread, &mut warnings).await;
+                let reviews = pr
+                    .fetch_reviews(github, thread.last_read_at)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warnings.push(format!("Could not fetch reviews: {}", e));
+                        Vec::new()
+                    });
+                let review_comments = pr
+                    .fetch_review_comments(github, thread.last_read_at)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warnings.push(format!("Could not fetch review comments: {}", e));
+                        Vec::new()
+                    });
+                let changed_files = if self.changed_files {
+                    Some(pr.fetch_files(github).await.unwrap_or_else(|e| {
+                        warnings.push(format!("Could not fetch changed files: {}", e));
+                        Vec::new()
+                    }))
+                } else {
+                    None
+                };
+                build_pull_request_notification_context(
+                    thread,
+                    pr,
+                    &comments_since_last_read,
+                    &reviews,
+                    &review_comments,
+                    changed_files.as_deref(),
+                    self.patch_budget,
+                    self.context_tokens,
+                )
             }
-            "Issue" => {
-                let issue = thread.fetch_issue().await?;
-                std::thread::sleep(std::time::Duration::from_secs(1));
+            Some(Subject::Issue(issue)) => {
                 let comments_since_last_read =
-                    fetch_comments_since_last_read(&issue, &thread, &opts).await;
-                build_issue_notification_context(&thread, &issue, &comments_since_last_read)
+                    fetch_comments_since_last_read(github, issue, thread, &mut warnings).await;
+                build_issue_notification_context(
+                    thread,
+                    issue,
+                    &comments_since_last_read,
+                    self.context_tokens,
+                )
             }
-            x => {
-                eprintln!("⚠ Skipping unsupported notification type: {}", x);
-                eprintln!(
-                    "   Notification: {} in {}",
-                    thread.subject.title, thread.repository.full_name
-                );
-                skipped += 1;
-                continue;
+            Some(Subject::Commit { commit, comments }) => {
+                let comments_since_last_read: Vec<_> = comments
+                    .iter()
+                    .filter(|c| thread.last_read_at.is_none_or(|read| c.created_at > read))
+                    .cloned()
+                    .collect();
+                build_commit_notification_context(
+                    thread,
+                    commit,
+                    &comments_since_last_read,
+                    self.context_tokens,
+                )
+            }
+            Some(Subject::Release(release)) => {
+                build_release_notification_context(thread, release, self.context_tokens)
+            }
+            Some(Subject::Discussion(discussion)) => {
+                build_discussion_notification_context(thread, discussion, self.context_tokens)
+            }
+            Some(Subject::WorkflowRun { run, jobs }) => {
+                build_workflow_run_notification_context(thread, run, jobs, self.context_tokens)
+            }
+            Some(Subject::SecurityAlerts(alerts)) => {
+                build_security_alert_notification_context(thread, alerts, self.context_tokens)
+            }
+            None => {
+                return Ok(match unavailable {
+                    Some(e) => Outcome::Unavailable(e),
+                    None => Outcome::Unsupported,
+                })
             }
         };
+        // Each decision gets its own manager so that decisions can run concurrently.
+        let mut manager = Manager::default();
+        for policy in &self.policies {
+            manager.add(policy.clone());
+        }
         let template = MessageCreateParams {
             max_tokens: 3333,
-            model: Model::Known(KnownModel::ClaudeSonnet40),
             messages: vec![],
             ..Default::default()
         };
-        let mut usage = Usage::default();
-        let report = manager
-            .apply(&client, template, &llm_prompt, Some(&mut usage))
-            .await?;
+        let mut policy_usage = Usage::default();
+        let report = apply_policies(
+            self.llm.as_ref(),
+            &mut manager,
+            template,
+            &llm_prompt,
+            Some(&mut policy_usage),
+        )
+        .await;
+        let mut usage = TokenUsage::from(&policy_usage);
+        self.record_usage(usage);
+        let report = report?;
 
-        display_notification_info(&thread, &opts);
+        let decision = Decision::from_value(report.value())?;
+        let explanation = explain(&report, &self.prompts);
+        let unavailable = || format!("Summary unavailable for: {}", thread.subject.title);
+        // Without a summary the decision is not cached, so it is made afresh once there is budget.
+        let summary = if self.over_budget().is_some() {
+            unavailable()
+        } else {
+            match generate_summary(self.llm.as_ref(), &llm_prompt).await {
+                Ok((summary, summary_usage)) => {
+                    self.record_usage(summary_usage);
+                    usage += summary_usage;
+                    let cached = CachedDecision {
+                        decision: decision.clone(),
+                        summary: summary.clone(),
+                        context: llm_prompt,
+                        explanation: explanation.clone(),
+                    };
+                    if let Some(cache) = &self.cache {
+                        if let Err(e) = cache.put(thread, &self.fingerprint, &cached) {
+                            warnings.push(format!("Could not cache decision: {}", e));
+                        }
+                    }
+                    summary
+                }
+                Err(e) => {
+                    warnings.push(format!("Could not generate summary: {}", e));
+                    unavailable()
+                }
+            }
+        };
+        Ok(Outcome::Decided(Decided {
+            decision,
+            summary,
+            source: DecisionSource::Model,
+            explanation,
+            usage: Some(usage),
+            warnings,
+        }))
+    }
+}
 
-        let decision: Decision = serde_json::from_value(report.value()).unwrap();
-        let summary = generate_summary(&client, &llm_prompt)
-            .await
-            .unwrap_or_else(|e| {
-                if !opts.quiet {
-                    eprintln!("⚠ Could not generate summary: {}", e);
+/// A notification and its decision
+type Pending = (Notification, Result<Outcome, ghai::Error>);
+
+/// The decisions for a batch of notifications, handed out in the order of the batch
+enum Decisions {
+    /// Each notification is decided when its turn comes
+    InTurn(std::vec::IntoIter<Notification>),
+    /// Every notification is decided in the background, at most `--jobs` at a time
+    Ahead(VecDeque<JoinHandle<Pending>>),
+}
+
+impl Decisions {
+    fn start(decider: &Arc<Decider>, notifications: Vec<Notification>, jobs: usize) -> Self {
+        if jobs <= 1 {
+            return Decisions::InTurn(notifications.into_iter());
+        }
+        // Waiting on GitHub's rate limit holds a permit, so a limited client slows every job.
+        let permits = Arc::new(Semaphore::new(jobs));
+        let handles = notifications
+            .into_iter()
+            .map(|thread| {
+                let decider = Arc::clone(decider);
+                let permits = Arc::clone(&permits);
+                tokio::spawn(async move {
+                    let _permit = permits
+                        .acquire_owned()
+                        .await
+                        .expect("the semaphore is never closed");
+                    let decided = decider.decide(&thread).await;
+                    (thread, decided)
+                })
+            })
+            .collect();
+        Decisions::Ahead(handles)
+    }
+
+    /// The next notification and its decision, once it has been decided
+    async fn next(&mut self, decider: &Decider) -> Option<Pending> {
+        match self {
+            Decisions::InTurn(notifications) => {
+                let thread = notifications.next()?;
+                let decided = decider.decide(&thread).await;
+                Some((thread, decided))
+            }
+            Decisions::Ahead(handles) => match handles.pop_front()?.await {
+                Ok(pending) => Some(pending),
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            },
+        }
+    }
+}
+
+impl Drop for Decisions {
+    /// Stop deciding notifications that will not be processed
+    fn drop(&mut self) {
+        if let Decisions::Ahead(handles) = self {
+            for handle in handles.iter() {
+                handle.abort();
+            }
+        }
+    }
+}
+
+/// Everything needed to decide and act on notifications
+struct Processor {
+    opts: Options,
+    decider: Arc<Decider>,
+    audit: Option<AuditLog>,
+    /// Where changes are journaled for `--undo`; `None` in dry runs
+    journal: Option<Run>,
+    tally: Tally,
+    queue: ReviewQueue,
+}
+
+impl Processor {
+    /// Process `notifications` in order, stopping early if the user quits or `stop` is set
+    async fn process_all(
+        &mut self,
+        notifications: Vec<Notification>,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<(), ghai::Error> {
+        let total = notifications.len();
+        if !self.opts.quiet {
+            println!("\n📊 Processing {} notifications...\n", total);
+        }
+        let decider = Arc::clone(&self.decider);
+        let mut decisions = Decisions::start(&decider, notifications, self.jobs());
+        for index in 0..total {
+            if stop() {
+                return Ok(());
+            }
+            if !self.opts.quiet && !self.opts.json {
+                println!("🔄 Processing {}/{}", index + 1, total);
+            }
+            let Some((thread, decided)) = decisions.next(&decider).await else {
+                break;
+            };
+            if self.process(&thread, decided).await? == Flow::Quit {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// How many notifications to decide at once
+    fn jobs(&self) -> usize {
+        self.opts.jobs.unwrap_or(1) as usize
+    }
+
+    /// Print what the GitHub client noticed without failing, unless told to be quiet
+    ///
+    /// Decisions made at once share the client, so a warning may belong to a notification decided
+    /// alongside the one being reported.
+    fn report_client_warnings(&self) {
+        let warnings = self.decider.github.take_warnings();
+        if !self.opts.quiet {
+            for warning in warnings {
+                eprintln!("⚠ {}", warning);
+            }
+        }
+    }
+
+    /// Report a notification's decision, carry it out, and record both in the audit log
+    async fn process(
+        &mut self,
+        thread: &Notification,
+        decided: Result<Outcome, ghai::Error>,
+    ) -> Result<Flow, ghai::Error> {
+        let opts = &self.opts;
+        self.tally.processed += 1;
+        let decided = match decided? {
+            Outcome::Decided(decided) => decided,
+            Outcome::Unsupported => {
+                eprintln!(
+                    "⚠ Skipping unsupported notification type: {}",
+                    thread.subject.r#type
+                );
+                eprintln!(
+                    "   Notification: {} in {}",
+                    thread.subject.title, thread.repository.full_name
+                );
+                self.tally.skipped += 1;
+                return Ok(Flow::Continue);
+            }
+            Outcome::Unavailable(e) => {
+                eprintln!(
+                    "⚠ Skipping notification whose subject is unavailable: {}",
+                    e
+                );
+                eprintln!(
+                    "   Notification: {} in {}",
+                    thread.subject.title, thread.repository.full_name
+                );
+                self.tally.skipped += 1;
+                return Ok(Flow::Continue);
+            }
+        };
+        display_notification_info(thread, opts);
+        match &decided.source {
+            DecisionSource::Cache => {
+                if opts.detailed {
+                    println!("♻ Unchanged since the last run; using the cached decision");
+                }
+                self.tally.cache_hits += 1;
+            }
+            DecisionSource::Rule => {
+                if opts.detailed {
+                    for rule in &decided.explanation.policies {
+                        println!("⚙ Decided by rule: {}", rule);
+                    }
+                }
+                self.tally.rule_decisions += 1;
+            }
+            DecisionSource::Budget => {
+                if self.tally.over_budget == 0 {
+                    if let Some(rationale) = &decided.explanation.rationale {
+                        eprintln!("⚠ {}; deciding the rest without it", rationale);
+                    }
                 }
-                format!("Summary unavailable for: {}", thread.subject.title)
-            });
+                self.tally.over_budget += 1;
+            }
+            _ => {}
+        }
+        if !opts.quiet {
+            for warning in &decided.warnings {
+                eprintln!("⚠ {}", warning);
+            }
+        }
+        self.report_client_warnings();
+        let mut entry = AuditEntry::new(
+            thread,
+            &decided.decision,
+            decided.source.clone(),
+            &decided.explanation,
+        );
+        entry.dry_run = self.opts.dry_run;
+        if matches!(
+            decided.source,
+            DecisionSource::Model | DecisionSource::Cache
+        ) {
+            entry.model = Some(self.decider.llm.model().to_string());
+        }
+        entry.usage = decided.usage;
+        // Record what was done even when a later step fails.
+        let flow = self.carry_out(thread, &decided, &mut entry.actions).await;
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(&entry) {
+                eprintln!("⚠ Could not write to the audit log: {}", e);
+            }
+        }
+        flow
+    }
+
+    /// Carry out a decision, noting each action in `actions` as it succeeds
+    async fn carry_out(
+        &mut self,
+        thread: &Notification,
+        decided: &Decided,
+        actions: &mut Vec<String>,
+    ) -> Result<Flow, ghai::Error> {
+        let opts = &self.opts;
+        let decision = &decided.decision;
+        let summary = &decided.summary;
 
         if !opts.quiet {
-            println!("{}", format_decision(&decision, &opts));
+            println!("{}", format_decision(decided, opts));
+        }
+        if opts.detailed && !opts.json {
+            for policy in &decided.explanation.policies {
+                println!("   Matched: {}", policy);
+            }
+            if let Some(rationale) = &decided.explanation.rationale {
+                println!("   Rationale: {}", rationale);
+            }
         }
 
-        if decision.action == "mark-unread" {
+        let steps = Step::plan(decision, opts);
+        // Threads the decision leaves unread wait in the review queue.
+        self.queue.remove(&thread.id);
+        if !steps.iter().any(|step| matches!(step, Step::Thread(_))) {
+            self.queue.push(QueueEntry::new(thread, decision, summary));
+        }
+        if steps.is_empty() {
+            if decision.action == Some(DecisionAction::MarkUnread) {
+                if !opts.quiet {
+                    println!("📌 Keeping as unread (no action needed)");
+                }
+                self.tally.marked_unread += 1;
+            } else {
+                self.tally.skipped += 1;
+                if !opts.quiet {
+                    println!("No action needed - skipping");
+                }
+            }
             if !opts.quiet {
-                println!("📌 Keeping as unread (no action needed)");
                 println!("Summary: {}", summary);
-                println!("URL: {}", thread.subject.url);
+                println!("URL: {}", thread.html_url());
             }
-            marked_unread += 1;
-        } else if decision.action == "mark-read"
-            || (decision.action.is_empty() && opts.mark_read_by_default)
-        {
-            let message = if decision.action == "mark-read" {
-                "Suggestion: mark as read"
-            } else {
-                "Default: mark as read"
-            };
-
+            return Ok(Flow::Continue);
+        }
+        if decision.action == Some(DecisionAction::MarkUnread) {
             if !opts.quiet {
-                println!("{}", message);
-            }
-            if !opts.dry_run {
-                match confirm_via_ui(&summary, "read", &opts) {
-                    Ok(true) => {
-                        thread.mark_as_read().await.unwrap();
-                        marked_read += 1;
-                        if !opts.quiet {
-                            println!("✓ Marked as read");
+                println!("📌 Keeping as unread");
+            }
+            self.tally.marked_unread += 1;
+        }
+
+        let plan = steps
+            .iter()
+            .map(|step| step.describe(thread))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !opts.quiet {
+            if decision.action.is_none() && matches!(steps[0], Step::Thread(_)) {
+                println!("Default: {}", plan);
+            } else {
+                println!("Suggestion: {}", plan);
+            }
+        }
+        if opts.dry_run {
+            self.tally.skipped += 1;
+            return Ok(Flow::Continue);
+        }
+        match confirm_via_ui(summary, &plan, opts) {
+            Ok(true) => {}
+            Ok(false) => {
+                self.tally.skipped += 1;
+                self.queue.push(QueueEntry::new(thread, decision, summary));
+                return Ok(Flow::Continue);
+            }
+            Err(()) => {
+                if !opts.quiet {
+                    println!("\n👋 Exiting at user request");
+                }
+                return Ok(Flow::Quit);
+            }
+        }
+        for step in &steps {
+            match step
+                .run(&self.decider.github, thread, &mut self.tally)
+                .await
+            {
+                Ok((done, change)) => {
+                    if let Some(change) = change {
+                        actions.push(step.name().to_string());
+                        if let Some(journal) = &mut self.journal {
+                            if let Err(e) = journal.record(thread, change) {
+                                eprintln!(
+                                    "⚠ Could not journal the change; it cannot be undone: {}",
+                                    e
+                                );
+                            }
                         }
                     }
-                    Ok(false) => {
-                        skipped += 1;
+                    if !opts.quiet {
+                        println!("{}", done);
                     }
-                    Err(()) => {
-                        if !opts.quiet {
-                            println!("\n👋 Exiting at user request");
+                }
+                // Starring and labeling are often refused on other people's repositories; that
+                // should not stop the run.
+                Err(e) if !matches!(step, Step::Thread(_)) => {
+                    eprintln!("⚠ Could not {}: {}", step.describe(thread), e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Poll for new and changed threads until interrupted
+    ///
+    /// Each poll is conditional on the previous one's `Last-Modified`, so an unchanged inbox costs
+    /// nothing against the rate limit.  Threads are tracked by the `updated_at` they were last
+    /// processed at; a thread that fails is retried the next time the inbox changes.
+    async fn watch(&mut self) -> Result<(), ghai::Error> {
+        let shutdown = shutdown_signal();
+        let stop = || *shutdown.borrow();
+        let mut changed = shutdown.clone();
+        let mut last_modified: Option<String> = None;
+        let mut seen: HashMap<String, DateTime<Utc>> = HashMap::new();
+        if !self.opts.quiet {
+            println!("👀 Watching notifications; press Ctrl-C to stop");
+        }
+        while !stop() {
+            let interval =
+                match Notification::poll(&self.decider.github, last_modified.as_deref()).await {
+                    Ok(poll) => {
+                        last_modified = poll.last_modified;
+                        if let Some(notifications) = poll.items {
+                            seen.retain(|id, _| notifications.iter().any(|n| &n.id == id));
+                            let fresh = notifications
+                                .into_iter()
+                                .filter(|n| seen.get(&n.id) != Some(&n.updated_at))
+                                .collect::<Vec<_>>();
+                            if !fresh.is_empty() && self.watch_pass(fresh, &mut seen, &stop).await {
+                                break;
+                            }
                         }
-                        std::process::exit(0);
+                        poll.poll_interval
+                    }
+                    Err(e) => {
+                        eprintln!("⚠ Could not poll notifications: {}", e);
+                        None
                     }
+                };
+            let delay = interval
+                .unwrap_or(DEFAULT_POLL_INTERVAL)
+                .max(Duration::from_secs(self.opts.poll_interval.unwrap_or(0)));
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = changed.changed() => {}
+            }
+        }
+        if !self.opts.quiet {
+            println!("\n👋 Stopped watching");
+        }
+        Ok(())
+    }
+
+    /// Process the threads of one poll, returning true if the user quit
+    async fn watch_pass(
+        &mut self,
+        fresh: Vec<Notification>,
+        seen: &mut HashMap<String, DateTime<Utc>>,
+        stop: &dyn Fn() -> bool,
+    ) -> bool {
+        if !self.opts.quiet {
+            println!(
+                "\n📊 Processing {} new or changed notifications...\n",
+                fresh.len()
+            );
+        }
+        let decider = Arc::clone(&self.decider);
+        let mut decisions = Decisions::start(&decider, fresh, self.jobs());
+        while let Some((thread, decided)) = decisions.next(&decider).await {
+            if stop() {
+                return false;
+            }
+            match self.process(&thread, decided).await {
+                Ok(Flow::Continue) => {
+                    seen.insert(thread.id.clone(), thread.updated_at);
+                }
+                Ok(Flow::Quit) => return true,
+                Err(e) => {
+                    eprintln!(
+                        "⚠ Could not process {} in {}: {}",
+                        thread.subject.title, thread.repository.full_name, e
+                    );
                 }
-            } else {
-                skipped += 1;
             }
-        } else {
-            skipped += 1;
-            if !opts.quiet {
-                println!("No action needed - skipping");
-                println!("Summary: {}", summary);
-                println!("URL: {}", thread.subject.url);
+        }
+        false
+    }
+
+    /// Print the review queue, or write it to `--queue-file`
+    fn write_queue(&self) -> Result<(), ghai::Error> {
+        let format = QueueFormat::from(self.opts.queue_format.as_deref().unwrap_or("text"));
+        let rendered = self.queue.render(&format);
+        match &self.opts.queue_file {
+            Some(path) => {
+                std::fs::write(path, rendered)?;
+                if !self.opts.quiet {
+                    println!(
+                        "📝 Wrote {} notifications to review to {}",
+                        self.queue.len(),
+                        path
+                    );
+                }
             }
+            // An explicitly requested format is printed even when quiet or empty.
+            None if self.opts.queue_format.is_some() => print!("{}", rendered),
+            None if !self.opts.quiet && !self.queue.is_empty() => print!("\n{}", rendered),
+            None => {}
         }
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        Ok(())
     }
 
-    if !opts.quiet {
+    fn report(&self) {
+        if self.opts.quiet {
+            return;
+        }
         println!("\n📊 Processing Complete!");
-        println!("   Total processed: {}", processed);
-        println!("   Marked as read: {}", marked_read);
-        println!("   Marked as unread: {}", marked_unread);
-        println!("   Skipped: {}", skipped);
+        println!("   Total processed: {}", self.tally.processed);
+        println!("   Marked as read: {}", self.tally.marked_read);
+        println!("   Marked as unread: {}", self.tally.marked_unread);
+        for (count, what) in [
+            (self.tally.marked_done, "Marked as done"),
+            (self.tally.unsubscribed, "Unsubscribed"),
+            (self.tally.ignored, "Ignored"),
+            (self.tally.starred, "Starred repositories"),
+            (self.tally.labeled, "Labeled"),
+        ] {
+            if count > 0 {
+                println!("   {}: {}", what, count);
+            }
+        }
+        println!("   Skipped: {}", self.tally.skipped);
+        if self.decider.cache.is_some() {
+            println!("   Decided from cache: {}", self.tally.cache_hits);
+        }
+        if !self.decider.rules.is_empty() {
+            println!("   Decided by rule: {}", self.tally.rule_decisions);
+        }
+        let spend = self.decider.spend.lock().unwrap().clone();
+        if self.opts.json {
+            #[derive(serde::Serialize)]
+            struct Shown<'a> {
+                usage: &'a Spend,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                estimated_cost: Option<Cost>,
+            }
+            let shown = Shown {
+                usage: &spend,
+                estimated_cost: spend.cost(&self.decider.budget.prices),
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&shown).unwrap_or_else(|_| "{}".to_string())
+            );
+        } else {
+            for (model, usage) in spend.models() {
+                println!(
+                    "   Tokens ({}): {} input, {} output",
+                    model, usage.input_tokens, usage.output_tokens
+                );
+            }
+            if let Some(cost) = spend
+                .cost(&self.decider.budget.prices)
+                .filter(|_| !spend.is_empty())
+            {
+                println!("   Estimated cost: {}", cost);
+            }
+        }
+        if self.tally.over_budget > 0 {
+            println!("   Over budget: {}", self.tally.over_budget);
+        }
+        if let Some(rate_limit) = self.decider.github.rate_limit() {
+            println!("   GitHub API requests remaining: {}", rate_limit.remaining);
+        }
+        if let Some(journal) = self.journal.as_ref().filter(|j| j.changes() > 0) {
+            println!(
+                "↩ To undo this run: ghai-process-notifications --undo {}",
+                journal.id()
+            );
+        }
     }
+}
 
+/// A flag that becomes true on SIGINT or SIGTERM
+fn shutdown_signal() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {}
+                        _ = terminate.recv() => {}
+                    }
+                }
+                Err(_) => {
+                    let _ = tokio::signal::ctrl_c().await;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+        let _ = tx.send(true);
+    });
+    rx
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (opts, args) = Options::from_command_line_relaxed(
+        "USAGE: ghai-process-notifications [options] [policy-files...]",
+    );
+
+    if opts.undo {
+        if args.len() > 1 {
+            eprintln!("❌ Error: --undo takes at most one run id");
+            std::process::exit(1);
+        }
+        let github = GitHubClient::new()?;
+        let journal = Journal::open()?;
+        return undo(&github, &journal, args.first().map(String::as_str)).await;
+    }
+    if opts.json && !opts.dry_run {
+        eprintln!("❌ Error: --json flag requires --dry-run flag");
+        eprintln!("   JSON output is only supported in dry-run mode to preview decisions");
+        std::process::exit(1);
+    }
+    if let Some(QueueFormat::Unknown(format)) = opts.queue_format.as_deref().map(QueueFormat::from)
+    {
+        eprintln!("❌ Error: unknown --queue-format '{}'", format);
+        eprintln!("   Choose text, markdown, or json");
+        std::process::exit(1);
+    }
+    if opts.jobs == Some(0) {
+        eprintln!("❌ Error: --jobs must be at least 1");
+        std::process::exit(1);
+    }
+    if opts.watch && !opts.no_confirm && !opts.dry_run {
+        eprintln!("❌ Error: --watch flag requires --no-confirm or --dry-run flag");
+        eprintln!("   Watch mode runs unattended and cannot prompt for confirmation");
+        std::process::exit(1);
+    }
+
+    let config = Config::load()?;
+    let llm = opts
+        .llm
+        .connect(&config.llm, "ghai-process-notifications", DEFAULT_MODEL)?;
+    if opts.max_cost.is_some() && ModelPrice::of(llm.model(), &config.llm.prices).is_none() {
+        eprintln!("❌ Error: no price is known for {}", llm.model());
+        eprintln!("   Add it to llm.prices in the config file to use --max-cost");
+        std::process::exit(1);
+    }
+    let github = GitHubClient::new()?;
+
+    // Read and concatenate all policy files
+    let mut concatenated_content = String::new();
+    for file_path in &args {
+        match std::fs::read_to_string(file_path) {
+            Ok(content) => {
+                if !opts.quiet {
+                    println!("📂 Loaded policy file: {}", file_path);
+                }
+                concatenated_content.push_str(&content);
+                concatenated_content.push('\n');
+            }
+            Err(e) => {
+                eprintln!("❌ Error reading policy file '{}': {}", file_path, e);
+                eprintln!("   Please check that the file exists and is readable.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let set = match parse_policies(&concatenated_content) {
+        Ok(set) => set,
+        Err(e @ ghai::Error::InvalidDecision { .. }) => {
+            eprintln!("❌ Error validating policy decision:");
+            eprintln!("   Error: {}", e);
+            eprintln!("   Please check the decision format in your policy file.");
+            std::process::exit(13);
+        }
+        Err(e) => {
+            eprintln!("❌ Error parsing policy file:");
+            eprintln!("   Error: {}", e);
+            eprintln!("   Please check the syntax of your policy file.");
+            std::process::exit(13);
+        }
+    };
+    let changed_files = opts.changed_files || opts.patch_budget.is_some();
+    let patch_budget = opts.patch_budget.unwrap_or(0) as usize;
+    // Decisions made from a different context may differ, so they are cached apart.
+    let mut fingerprinted = concatenated_content.clone();
+    if changed_files {
+        fingerprinted.push_str(&format!(
+            "\0changed files, {} patch characters",
+            patch_budget
+        ));
+    }
+    if let Some(context_tokens) = opts.context_tokens {
+        fingerprinted.push_str(&format!("\0{} context tokens", context_tokens));
+    }
+    let context_tokens = opts
+        .context_tokens
+        .map_or(DEFAULT_CONTEXT_TOKENS, |tokens| tokens as usize);
+    let fingerprint = fingerprint(&fingerprinted, llm.model());
+    let cache = if opts.no_cache {
+        None
+    } else {
+        match DecisionCache::open() {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!("⚠ Decision cache unavailable: {}", e);
+                None
+            }
+        }
+    };
+
+    let audit = match AuditLog::open() {
+        Ok(audit) => Some(audit),
+        Err(e) => {
+            eprintln!("⚠ Audit log unavailable: {}", e);
+            None
+        }
+    };
+
+    let journal = if opts.dry_run {
+        None
+    } else {
+        match Journal::open() {
+            Ok(journal) => Some(journal.start_run()),
+            Err(e) => {
+                eprintln!("⚠ Journal unavailable; this run cannot be undone: {}", e);
+                None
+            }
+        }
+    };
+
+    let prompts = set
+        .policies
+        .iter()
+        .map(|policy| policy.prompt.clone())
+        .collect();
+    let decider = Decider {
+        github,
+        llm,
+        rules: set.rules,
+        policies: set.policies,
+        prompts,
+        cache,
+        fingerprint,
+        changed_files,
+        patch_budget,
+        context_tokens,
+        budget: Budget {
+            max_tokens: opts.max_tokens_budget,
+            max_cost: opts.max_cost,
+            prices: config.llm.prices,
+        },
+        spend: Mutex::new(Spend::default()),
+    };
+    let mut processor = Processor {
+        opts,
+        decider: Arc::new(decider),
+        audit,
+        journal,
+        tally: Tally::default(),
+        queue: ReviewQueue::new(),
+    };
+    if processor.opts.watch {
+        processor.watch().await?;
+    } else {
+        let notifications =
+            Notification::fetch_all::<Utc>(&processor.decider.github, false, false, None, None)
+                .await?;
+        processor.process_all(notifications, &|| false).await?;
+    }
+    processor.report_client_warnings();
+    processor.report();
+    processor.write_queue()?;
+
+    Ok(())
+}
+
+/// Undo the changes journaled for `run_id`, or for the most recent run, newest first
+///
+/// Subscriptions, stars, and labels are restored.  GitHub cannot mark a thread unread, so threads
+/// the run marked read or done are listed instead.  A run is only set aside once every change that
+/// can be undone has been.
+async fn undo(
+    github: &GitHubClient,
+    journal: &Journal,
+    run_id: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let run_id = match run_id {
+        Some(run_id) => run_id.to_string(),
+        None => match journal.runs()?.pop() {
+            Some(run_id) => run_id,
+            None => {
+                println!("Nothing to undo");
+                return Ok(());
+            }
+        },
+    };
+    let entries = journal.entries(&run_id)?;
+    println!("↩ Undoing run {} ({} changes)", run_id, entries.len());
+    let mut failed = 0;
+    let mut still_read = Vec::new();
+    for entry in entries.iter().rev() {
+        let thread = &entry.thread;
+        let undone = match &entry.change {
+            Change::Unsubscribe | Change::Ignore => thread
+                .resubscribe(github)
+                .await
+                .map(|()| Some(format!("🔔 Resubscribed to {}", thread.subject.title))),
+            Change::Star => thread
+                .unstar_repository(github)
+                .await
+                .map(|()| Some(format!("☆ Unstarred {}", thread.repository.full_name))),
+            Change::Label { labels } => {
+                let mut result = Ok(());
+                for label in labels {
+                    match thread.remove_label(github, label).await {
+                        // Already gone, perhaps by an earlier attempt at this undo.
+                        Ok(()) | Err(ghai::Error::NotFound(_)) => {}
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+                result.map(|()| {
+                    Some(format!(
+                        "🏷  Removed {} from {}",
+                        labels.join(", "),
+                        thread.subject.title
+                    ))
+                })
+            }
+            Change::MarkRead | Change::MarkDone => Ok(None),
+        };
+        match undone {
+            Ok(Some(message)) => println!("{}", message),
+            Ok(None) => {}
+            Err(e) => {
+                failed += 1;
+                eprintln!(
+                    "⚠ Could not undo {} on {}: {}",
+                    entry.change, thread.subject.title, e
+                );
+            }
+        }
+        if entry.change.marks_read() {
+            let url = thread.html_url();
+            if !still_read.contains(&url) {
+                still_read.push(url);
+            }
+        }
+    }
+    if !still_read.is_empty() {
+        println!("\nGitHub cannot mark threads unread; these were marked read or done:");
+        for url in &still_read {
+            println!("   {}", url);
+        }
+    }
+    if failed > 0 {
+        eprintln!(
+            "❌ {} changes could not be undone; run --undo {} again to retry",
+            failed, run_id
+        );
+        std::process::exit(1);
+    }
+    journal.mark_undone(&run_id)?;
     Ok(())
 }
 
 async fn fetch_comments_since_last_read(
+    github: &GitHubClient,
     fetcher: &impl CommentFetcher,
     thread: &Notification,
-    opts: &Options,
+    warnings: &mut Vec<String>,
 ) -> Vec<IssueComment> {
-    let since_time = thread
-        .last_read_at
-        .as_ref()
-        .and_then(|t| t.parse::<DateTime<FixedOffset>>().ok());
-
     fetcher
-        .fetch_comments(since_time)
+        .fetch_comments(github, thread.last_read_at)
         .await
         .unwrap_or_else(|e| {
-            if !opts.quiet {
-                eprintln!("⚠ Could not fetch comments: {}", e);
-            }
+            warnings.push(format!("Could not fetch comments: {}", e));
             Vec::new()
         })
 }
@@ -2,11 +2,22 @@
 
 use std::io::Write;
 
+use arrrg::CommandLine;
 use futures::StreamExt;
 
-use claudius::{
-    Anthropic, ContentBlockDelta, MessageCreateParams, MessageStreamEvent, Model, SystemPrompt,
-};
+use claudius::{MessageCreateParams, SystemPrompt};
+
+use ghai::config::Config;
+use ghai::llm::{Llm, LlmOptions};
+
+#[derive(Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
+struct Options {
+    #[arrrg(nested)]
+    llm: LlmOptions,
+}
+
+/// The model used when neither the command line nor the config file chooses one
+const DEFAULT_MODEL: &str = "claude-haiku-4-5";
 
 const BASE_SYSTEM_PROMPT: &str = r#"You are a professional technical writer who transforms informal issue descriptions into well-structured GitHub issues.
 
@@ -44,32 +55,23 @@ fn load_system_prompt() -> Result<String, Box<dyn std::error::Error>> {
     Ok(combined)
 }
 
-async fn rewrite_issue(input: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let client = Anthropic::new(None)?;
+async fn rewrite_issue(llm: &dyn Llm, input: &str) -> Result<(), Box<dyn std::error::Error>> {
     let system_prompt = load_system_prompt()?;
 
     let req = MessageCreateParams {
         max_tokens: 1024,
-        model: Model::Custom("claude-haiku-4-5".to_string()),
         messages: vec![input.into()],
         system: Some(SystemPrompt::String(system_prompt)),
         ..Default::default()
     };
 
-    let stream = client.stream(req).await?;
-
-    // Pin the stream so it can be polled
-    tokio::pin!(stream);
+    let mut stream = llm.stream(req).await?;
 
-    while let Some(event) = stream.next().await {
-        match event {
-            Ok(event) => {
-                if let MessageStreamEvent::ContentBlockDelta(ref x) = event {
-                    if let ContentBlockDelta::TextDelta(ref text_delta) = x.delta {
-                        print!("{}", text_delta.text);
-                        let _ = std::io::stdout().flush();
-                    }
-                }
+    while let Some(text) = stream.next().await {
+        match text {
+            Ok(text) => {
+                print!("{}", text);
+                let _ = std::io::stdout().flush();
             }
             Err(e) => {
                 eprintln!("Error: {e}");
@@ -84,11 +86,18 @@ async fn rewrite_issue(input: &str) -> Result<(), Box<dyn std::error::Error>> {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    let (opts, args) = Options::from_command_line_relaxed(
+        "USAGE: ghai-rewrite-issue [options] My issue description",
+    );
+    let input = args.join(" ");
     if input.trim().is_empty() {
-        eprintln!("USAGE: ghai-rewrite-issue My issue description");
+        eprintln!("USAGE: ghai-rewrite-issue [options] My issue description");
         std::process::exit(13);
     }
-    rewrite_issue(&input).await?;
+    let config = Config::load()?;
+    let llm = opts
+        .llm
+        .connect(&config.llm, "ghai-rewrite-issue", DEFAULT_MODEL)?;
+    rewrite_issue(llm.as_ref(), &input).await?;
     Ok(())
 }
@@ -0,0 +1,299 @@
+//! Accounting for the tokens a run spends, and limits on them.
+//!
+//! Every model call's usage is added to a [`Spend`], per model.  A [`Budget`] caps the tokens or the
+//! estimated cost of a run; once it is spent, the processor stops calling the model.  Costs are
+//! estimated from a built-in table of Anthropic list prices, which the `llm.prices` section of the
+//! configuration file overrides or extends, e.g. to price a local model at zero:
+//!
+//! ```json
+//! {"llm": {"prices": {"llama3.2": {"input": 0, "output": 0}}}}
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::audit::TokenUsage;
+
+//////////////////////////////////////////////// Cost //////////////////////////////////////////////
+
+/// An amount of US dollars, held exactly in billionths of a dollar
+///
+/// Written and parsed as dollars, e.g. `2.5` or `$2.50`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cost(u64);
+
+impl Cost {
+    const PER_DOLLAR: u64 = 1_000_000_000;
+
+    /// `None` if `dollars` is negative or not finite
+    pub fn from_dollars(dollars: f64) -> Option<Self> {
+        if !dollars.is_finite() || dollars < 0.0 {
+            return None;
+        }
+        Some(Self((dollars * Self::PER_DOLLAR as f64).round() as u64))
+    }
+
+    pub fn dollars(&self) -> f64 {
+        self.0 as f64 / Self::PER_DOLLAR as f64
+    }
+}
+
+impl std::ops::Add for Cost {
+    type Output = Cost;
+
+    fn add(self, other: Cost) -> Cost {
+        Cost(self.0.saturating_add(other.0))
+    }
+}
+
+impl std::str::FromStr for Cost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dollars = s.trim().trim_start_matches('$');
+        dollars
+            .parse::<f64>()
+            .ok()
+            .and_then(Cost::from_dollars)
+            .ok_or_else(|| format!("expected an amount of dollars, e.g. 2.50, got '{}'", s))
+    }
+}
+
+impl std::fmt::Display for Cost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "${:.4}", self.dollars())
+    }
+}
+
+impl serde::Serialize for Cost {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.dollars())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Cost {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let dollars = f64::deserialize(deserializer)?;
+        Cost::from_dollars(dollars).ok_or_else(|| {
+            serde::de::Error::custom(format!("expected a non-negative price, got {}", dollars))
+        })
+    }
+}
+
+///////////////////////////////////////////// ModelPrice ///////////////////////////////////////////
+
+/// What a model charges per million tokens
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    pub input: Cost,
+    pub output: Cost,
+}
+
+/// Anthropic list prices in dollars per million input and output tokens, most specific first
+const LIST_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-3-haiku", 0.25, 1.25),
+];
+
+impl ModelPrice {
+    /// The price of `model`: its entry in `overrides`, or else its list price, if known
+    pub fn of(model: &str, overrides: &BTreeMap<String, ModelPrice>) -> Option<ModelPrice> {
+        if let Some(price) = overrides.get(model) {
+            return Some(*price);
+        }
+        LIST_PRICES
+            .iter()
+            .find(|(prefix, _, _)| model.starts_with(prefix))
+            .map(|(_, input, output)| ModelPrice {
+                input: Cost::from_dollars(*input).unwrap_or_default(),
+                output: Cost::from_dollars(*output).unwrap_or_default(),
+            })
+    }
+
+    /// The cost of `usage` at this price
+    pub fn cost(&self, usage: &TokenUsage) -> Cost {
+        let per_million = |price: Cost, tokens: u64| {
+            Cost((price.0 as u128 * tokens as u128 / 1_000_000).min(u64::MAX as u128) as u64)
+        };
+        per_million(self.input, usage.input_tokens) + per_million(self.output, usage.output_tokens)
+    }
+}
+
+/////////////////////////////////////////////// Spend //////////////////////////////////////////////
+
+/// The tokens a run has spent, per model
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize)]
+#[serde(transparent)]
+pub struct Spend {
+    models: BTreeMap<String, TokenUsage>,
+}
+
+impl Spend {
+    /// Count `usage` against `model`
+    pub fn add(&mut self, model: &str, usage: TokenUsage) {
+        *self.models.entry(model.to_string()).or_default() += usage;
+    }
+
+    /// The usage of each model, in order of name
+    pub fn models(&self) -> impl Iterator<Item = (&str, &TokenUsage)> {
+        self.models
+            .iter()
+            .map(|(model, usage)| (model.as_str(), usage))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Input and output tokens across every model
+    pub fn tokens(&self) -> u64 {
+        self.models.values().map(TokenUsage::total).sum()
+    }
+
+    /// The estimated cost across every model, or `None` if some model has no known price
+    pub fn cost(&self, prices: &BTreeMap<String, ModelPrice>) -> Option<Cost> {
+        self.models
+            .iter()
+            .try_fold(Cost::default(), |total, (model, usage)| {
+                Some(total + ModelPrice::of(model, prices)?.cost(usage))
+            })
+    }
+}
+
+/////////////////////////////////////////////// Budget /////////////////////////////////////////////
+
+/// Limits on what a run may spend
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Budget {
+    /// Input and output tokens, across every model
+    pub max_tokens: Option<u64>,
+    /// Estimated cost, across every model
+    pub max_cost: Option<Cost>,
+    /// Prices that override the list prices
+    pub prices: BTreeMap<String, ModelPrice>,
+}
+
+impl Budget {
+    /// Why `spend` leaves nothing to spend, or `None` if there is budget left
+    ///
+    /// A model with no known price counts as having spent the cost budget, since its spend cannot
+    /// be checked.
+    pub fn exhausted(&self, spend: &Spend) -> Option<String> {
+        if let Some(max_tokens) = self.max_tokens {
+            if spend.tokens() >= max_tokens {
+                return Some(format!(
+                    "the token budget of {} is spent ({} used)",
+                    max_tokens,
+                    spend.tokens()
+                ));
+            }
+        }
+        if let Some(max_cost) = self.max_cost {
+            match spend.cost(&self.prices) {
+                Some(cost) if cost >= max_cost => {
+                    return Some(format!(
+                        "the cost budget of {} is spent ({} estimated)",
+                        max_cost, cost
+                    ));
+                }
+                Some(_) => {}
+                None => return Some("the cost of the model is unknown".to_string()),
+            }
+        }
+        None
+    }
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens,
+            output_tokens,
+            iterations: 1,
+        }
+    }
+
+    #[test]
+    fn costs_parse_and_print() {
+        assert_eq!(Ok(Cost::from_dollars(2.5).unwrap()), "$2.50".parse());
+        assert_eq!(Ok(Cost::from_dollars(0.1).unwrap()), "0.1".parse());
+        assert!("-1".parse::<Cost>().is_err());
+        assert!("lots".parse::<Cost>().is_err());
+        assert_eq!("$0.0123", Cost::from_dollars(0.0123).unwrap().to_string());
+        let price: ModelPrice = serde_json::from_str(r#"{"input": 0.8, "output": 4}"#).unwrap();
+        assert_eq!(Cost::from_dollars(0.8).unwrap(), price.input);
+        assert!(serde_json::from_str::<ModelPrice>(r#"{"input": -1, "output": 4}"#).is_err());
+    }
+
+    #[test]
+    fn spend_is_priced_per_model() {
+        let mut spend = Spend::default();
+        spend.add("claude-sonnet-4-0", usage(1_000_000, 0));
+        spend.add("claude-sonnet-4-0", usage(0, 100_000));
+        spend.add("claude-3-5-haiku-latest", usage(500_000, 0));
+        assert_eq!(1_600_000, spend.tokens());
+        assert_eq!(
+            vec![
+                ("claude-3-5-haiku-latest", &usage(500_000, 0)),
+                (
+                    "claude-sonnet-4-0",
+                    &TokenUsage {
+                        input_tokens: 1_000_000,
+                        output_tokens: 100_000,
+                        iterations: 2
+                    }
+                ),
+            ],
+            spend.models().collect::<Vec<_>>()
+        );
+        // $3 + $1.50 for sonnet and $0.40 for haiku
+        let prices = BTreeMap::new();
+        assert_eq!(Cost::from_dollars(4.9), spend.cost(&prices));
+
+        spend.add("llama3.2", usage(10, 10));
+        assert_eq!(None, spend.cost(&prices));
+        let prices = BTreeMap::from([("llama3.2".to_string(), ModelPrice::default())]);
+        assert_eq!(Cost::from_dollars(4.9), spend.cost(&prices));
+    }
+
+    #[test]
+    fn budgets_run_out() {
+        let mut spend = Spend::default();
+        let budget = Budget {
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(None, budget.exhausted(&spend));
+        spend.add("claude-sonnet-4-0", usage(900, 99));
+        assert_eq!(None, budget.exhausted(&spend));
+        spend.add("claude-sonnet-4-0", usage(0, 1));
+        assert!(budget
+            .exhausted(&spend)
+            .unwrap()
+            .contains("token budget of 1000"));
+
+        let budget = Budget {
+            max_cost: Cost::from_dollars(0.01),
+            ..Default::default()
+        };
+        // 1000 input and 100 output tokens of sonnet cost $0.0045.
+        spend.add("claude-sonnet-4-0", usage(100, 0));
+        assert_eq!(None, budget.exhausted(&spend));
+        spend.add("claude-sonnet-4-0", usage(2000, 200));
+        assert!(budget.exhausted(&spend).unwrap().contains("cost budget"));
+        spend.add("llama3.2", usage(1, 1));
+        assert!(budget.exhausted(&spend).unwrap().contains("unknown"));
+    }
+}
@@ -0,0 +1,197 @@
+//! An on-disk cache of notification decisions.
+//!
+//! Deciding a notification costs a subject fetch, a comments fetch, and two model calls.  The
+//! outcome only changes when the thread does or when the policies do, so each decision is stored
+//! under `$XDG_CACHE_HOME/ghai/decisions/<thread id>.json` together with the thread's `updated_at`
+//! and `last_read_at` and a fingerprint of the policies and model that produced it.  The context
+//! shows what happened since the thread was last read, so reading it in the browser changes the
+//! context as much as new activity does.  A lookup that disagrees on any of these is a miss, and
+//! the next store replaces the stale entry.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::config::cache_home;
+use crate::error::Error;
+use crate::policy::{Decision, Explanation};
+use crate::types::Notification;
+
+/////////////////////////////////////////// CachedDecision /////////////////////////////////////////
+
+/// Everything the processor derived for one version of a thread
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CachedDecision {
+    pub decision: Decision,
+    /// The one-line summary shown when confirming
+    pub summary: String,
+    /// The XML context the policies were applied to
+    pub context: String,
+    /// Entries written before explanations were kept have none
+    #[serde(default)]
+    pub explanation: Explanation,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Entry {
+    updated_at: DateTime<Utc>,
+    last_read_at: Option<DateTime<Utc>>,
+    fingerprint: String,
+    #[serde(flatten)]
+    cached: CachedDecision,
+}
+
+/////////////////////////////////////////// DecisionCache //////////////////////////////////////////
+
+pub struct DecisionCache {
+    dir: PathBuf,
+}
+
+impl DecisionCache {
+    /// The cache in its default location
+    pub fn open() -> Result<Self, Error> {
+        let dir = cache_home().ok_or_else(|| {
+            Error::Config("cannot find a cache directory; set XDG_CACHE_HOME or HOME".to_string())
+        })?;
+        Ok(Self::at(dir.join("ghai").join("decisions")))
+    }
+
+    /// A cache stored in `dir`
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The decision for this version of `thread`, if one was made with the same fingerprint
+    ///
+    /// Unreadable or corrupt entries are treated as misses.
+    pub fn get(&self, thread: &Notification, fingerprint: &str) -> Option<CachedDecision> {
+        let content = std::fs::read(self.path(thread)).ok()?;
+        let entry: Entry = serde_json::from_slice(&content).ok()?;
+        (entry.updated_at == thread.updated_at
+            && entry.last_read_at == thread.last_read_at
+            && entry.fingerprint == fingerprint)
+            .then_some(entry.cached)
+    }
+
+    /// Remember the decision for this version of `thread`
+    pub fn put(
+        &self,
+        thread: &Notification,
+        fingerprint: &str,
+        cached: &CachedDecision,
+    ) -> Result<(), Error> {
+        let entry = Entry {
+            updated_at: thread.updated_at,
+            last_read_at: thread.last_read_at,
+            fingerprint: fingerprint.to_string(),
+            cached: cached.clone(),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(thread);
+        // Write then rename so a concurrent reader never sees a partial entry.
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(
+            &tmp,
+            serde_json::to_vec_pretty(&entry).expect("entries serialize"),
+        )?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn path(&self, thread: &Notification) -> PathBuf {
+        // Thread ids are numeric, but keep the file name safe regardless.
+        let name = thread
+            .id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+/// A stable fingerprint of the policies and model that decide notifications
+///
+/// This is FNV-1a rather than `std`'s hasher because the latter may change between releases,
+/// which would silently invalidate every cache entry.
+pub fn fingerprint(policies: &str, model: &str) -> String {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = OFFSET;
+    for byte in policies.bytes().chain([0]).chain(model.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    format!("{:016x}", hash)
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{DecisionAction, Priority};
+
+    fn thread(updated_at: &str) -> Notification {
+        let mut thread = crate::fixture_thread("notifications_release_only.json", 0);
+        thread.updated_at = updated_at.parse().unwrap();
+        thread
+    }
+
+    fn cached() -> CachedDecision {
+        CachedDecision {
+            decision: Decision {
+                action: Some(DecisionAction::MarkRead),
+                priority: Some(Priority::Low),
+                label: vec!["release".to_string()],
+                star_repo: false,
+                apply_label: false,
+            },
+            summary: "ghai 0.7.0".to_string(),
+            context: "<notification/>".to_string(),
+            explanation: Explanation {
+                policies: vec!["If it is a release, output".to_string()],
+                rationale: None,
+            },
+        }
+    }
+
+    fn scratch(name: &str) -> DecisionCache {
+        let dir = std::env::temp_dir().join(format!("ghai-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        DecisionCache::at(dir)
+    }
+
+    #[test]
+    fn round_trip() {
+        let cache = scratch("round-trip");
+        let thread = thread("2024-05-01T12:00:00Z");
+        assert_eq!(cache.get(&thread, "f"), None);
+        cache.put(&thread, "f", &cached()).unwrap();
+        assert_eq!(cache.get(&thread, "f"), Some(cached()));
+        std::fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn stale_entries_miss() {
+        let cache = scratch("stale");
+        cache
+            .put(&thread("2024-05-01T12:00:00Z"), "f", &cached())
+            .unwrap();
+        assert_eq!(cache.get(&thread("2024-05-02T12:00:00Z"), "f"), None);
+        assert_eq!(cache.get(&thread("2024-05-01T12:00:00Z"), "g"), None);
+        let mut read = thread("2024-05-01T12:00:00Z");
+        read.last_read_at = Some(read.updated_at);
+        assert_eq!(cache.get(&read, "f"), None);
+        std::fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        assert_eq!(fingerprint("", ""), "af63bd4c8601b7df");
+        assert_ne!(fingerprint("a", "b"), fingerprint("ab", ""));
+    }
+}
@@ -0,0 +1,147 @@
+//! The ghai configuration file.
+//!
+//! Configuration lives in `$GHAI_CONFIG`, or `$XDG_CONFIG_HOME/ghai/config.json` (falling back to
+//! `~/.config/ghai/config.json`).  A missing file is equivalent to an empty configuration.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::budget::ModelPrice;
+use crate::error::Error;
+use crate::llm::Backend;
+
+///////////////////////////////////////////// Config ///////////////////////////////////////////////
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Base URL of the GitHub REST API, e.g. `https://github.example.com/api/v3`
+    pub api_url: Option<String>,
+    /// GitHub host to talk to, e.g. `github.example.com`
+    pub host: Option<String>,
+    /// Shell command that prints a GitHub token, tried when no other token source has one
+    pub token_command: Option<String>,
+    /// The language model used by binaries that call one
+    pub llm: LlmConfig,
+}
+
+impl Config {
+    /// Load the configuration from its default location
+    pub fn load() -> Result<Self, Error> {
+        match Self::path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Load the configuration from `path`, returning the default configuration if it is missing
+    pub fn load_from(path: &std::path::Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                Error::Config(format!("invalid config file {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Config(format!(
+                "could not read config file {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// The path of the configuration file
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("GHAI_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        config_home().map(|dir| dir.join("ghai").join("config.json"))
+    }
+}
+
+//////////////////////////////////////////// LlmConfig /////////////////////////////////////////////
+
+/// The `llm` section of the configuration file
+///
+/// Command-line flags take precedence over every field here.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    /// `anthropic` (the default), `openai`, or `ollama`
+    pub backend: Option<Backend>,
+    /// Base URL of the backend's API, e.g. `http://localhost:11434/v1`
+    pub url: Option<String>,
+    /// Model used by every binary without an entry in `models`
+    pub model: Option<String>,
+    /// Model to use per binary, e.g. `{"ghai-rewrite-issue": "llama3.2"}`
+    pub models: BTreeMap<String, String>,
+    /// Dollars per million tokens, per model, overriding the built-in list prices
+    pub prices: BTreeMap<String, ModelPrice>,
+}
+
+//////////////////////////////////////////// directories ///////////////////////////////////////////
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`
+pub fn config_home() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_CACHE_HOME`, falling back to `~/.cache`
+pub fn cache_home() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`
+pub fn state_home() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_parses_fields() {
+        let config: Config =
+            serde_json::from_str(r#"{"api_url": "http://localhost:8080"}"#).unwrap();
+        assert_eq!(config.api_url.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(config.host, None);
+    }
+
+    #[test]
+    fn config_rejects_unknown_fields() {
+        assert!(serde_json::from_str::<Config>(r#"{"api_uri": "typo"}"#).is_err());
+    }
+
+    #[test]
+    fn config_parses_llm_section() {
+        let config: Config = serde_json::from_str(
+            r#"{"llm": {"backend": "ollama", "models": {"ghai-rewrite-issue": "llama3.2"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.llm.backend, Some(Backend::Ollama));
+        assert_eq!(config.llm.model, None);
+        assert_eq!(
+            config
+                .llm
+                .models
+                .get("ghai-rewrite-issue")
+                .map(String::as_str),
+            Some("llama3.2")
+        );
+    }
+
+    #[test]
+    fn missing_config_is_default() {
+        let config = Config::load_from(std::path::Path::new("/nonexistent/ghai/config.json"));
+        assert_eq!(config.unwrap(), Config::default());
+    }
+}
@@ -0,0 +1,349 @@
+//! The error type shared by every part of the ghai library.
+
+use crate::parser::{ParseError, ParseErrorWithContext};
+
+///////////////////////////////////////////// ApiError /////////////////////////////////////////////
+
+/// A non-success response from the GitHub API
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiError {
+    /// The URL that was requested
+    pub url: String,
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The `message` from GitHub's error body, if there was one
+    pub message: Option<String>,
+    /// The `documentation_url` from GitHub's error body, if there was one
+    pub documentation_url: Option<String>,
+}
+
+impl ApiError {
+    /// Build an error from a response's URL, status, and (possibly empty) body
+    pub fn from_body(url: impl Into<String>, status: u16, body: &[u8]) -> Self {
+        #[derive(serde::Deserialize)]
+        struct Body {
+            message: Option<String>,
+            documentation_url: Option<String>,
+        }
+        let body = serde_json::from_slice::<Body>(body).ok();
+        Self {
+            url: url.into(),
+            status,
+            message: body.as_ref().and_then(|b| b.message.clone()),
+            documentation_url: body.and_then(|b| b.documentation_url),
+        }
+    }
+
+    /// Whether the message says a rate limit was exceeded, as GitHub's secondary limits do
+    pub fn is_rate_limit(&self) -> bool {
+        self.message
+            .as_deref()
+            .is_some_and(|m| m.to_lowercase().contains("rate limit"))
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {} from {}", self.status, self.url)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        if let Some(documentation_url) = &self.documentation_url {
+            write!(f, " (see {})", documentation_url)?;
+        }
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////// Error //////////////////////////////////////////////
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// GitHub rejected the credentials (401)
+    Unauthorized(ApiError),
+    /// The resource does not exist or is not visible to the token (404)
+    NotFound(ApiError),
+    /// The token may not see the resource, e.g. for want of admin rights or a scope (403)
+    Forbidden(ApiError),
+    /// A primary or secondary rate limit was still exceeded after retrying
+    RateLimited(ApiError),
+    /// Any other non-success response
+    Api(ApiError),
+    /// A response body did not match the expected type
+    Decode {
+        url: String,
+        /// Path to the offending value, e.g. `[3].repository.owner.login`
+        path: String,
+        source: serde_json::Error,
+    },
+    /// The request could not be sent or its response could not be read
+    Network {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// No GitHub token could be found; the message lists every source tried
+    NoToken(String),
+    /// The ghai configuration file is unreadable or invalid
+    Config(String),
+    /// A notification was asked for a subject of a type it does not have
+    UnexpectedSubject {
+        expected: String,
+        actual: String,
+    },
+    /// The subject of a notification could not be found
+    MissingSubject(String),
+    /// A GraphQL query answered with errors instead of data
+    GraphQl {
+        url: String,
+        messages: Vec<String>,
+    },
+    /// A policy line could not be parsed
+    Parse(ParseError),
+    /// A policy file could not be parsed, with the offending line
+    ParseWithContext(ParseErrorWithContext),
+    /// A policy decision did not match the `Decision` type
+    InvalidDecision {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A `when` rule in a policy file could not be parsed
+    InvalidRule {
+        rule: String,
+        message: String,
+    },
+    /// Applying policies to a notification failed
+    Policy(policyai::ApplyError),
+    /// The Anthropic API returned an error
+    Anthropic(claudius::Error),
+    /// A language model backend failed or returned a response ghai cannot use
+    Llm(String),
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// Classify a non-success response by its status and headers
+    pub fn from_response(api_error: ApiError, rate_limit_exhausted: bool) -> Self {
+        match api_error.status {
+            401 => Self::Unauthorized(api_error),
+            404 => Self::NotFound(api_error),
+            429 => Self::RateLimited(api_error),
+            403 if rate_limit_exhausted || api_error.is_rate_limit() => {
+                Self::RateLimited(api_error)
+            }
+            403 => Self::Forbidden(api_error),
+            _ => Self::Api(api_error),
+        }
+    }
+
+    /// Wrap a transport error from reqwest
+    pub fn network(url: impl Into<String>, source: reqwest::Error) -> Self {
+        Self::Network {
+            url: url.into(),
+            source,
+        }
+    }
+
+    /// The API error details, if this error came from a GitHub response
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Unauthorized(e)
+            | Self::NotFound(e)
+            | Self::Forbidden(e)
+            | Self::RateLimited(e)
+            | Self::Api(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code, if this error came from a GitHub response
+    pub fn status(&self) -> Option<u16> {
+        self.api_error().map(|e| e.status)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized(e) => write!(f, "unauthorized: {}", e),
+            Self::NotFound(e) => write!(f, "not found: {}", e),
+            Self::Forbidden(e) => write!(f, "forbidden: {}", e),
+            Self::RateLimited(e) => write!(f, "rate limited: {}", e),
+            Self::Api(e) => write!(f, "GitHub API error: {}", e),
+            Self::Decode { url, path, source } => {
+                write!(
+                    f,
+                    "could not decode response from {} at {}: {}",
+                    url, path, source
+                )
+            }
+            Self::Network { url, source } => write!(f, "request to {} failed: {}", url, source),
+            Self::NoToken(message) => write!(f, "{}", message),
+            Self::Config(message) => write!(f, "configuration error: {}", message),
+            Self::UnexpectedSubject { expected, actual } => {
+                write!(f, "expected a {} notification, got {}", expected, actual)
+            }
+            Self::MissingSubject(message) => write!(f, "subject not found: {}", message),
+            Self::GraphQl { url, messages } => {
+                write!(f, "GraphQL error from {}: {}", url, messages.join("; "))
+            }
+            Self::Parse(e) => write!(f, "{}", e),
+            Self::ParseWithContext(e) => write!(f, "{}", e),
+            Self::InvalidDecision { path, source } => {
+                write!(f, "invalid policy decision at {}: {}", path, source)
+            }
+            Self::InvalidRule { rule, message } => {
+                write!(f, "invalid rule `{}`: {}", rule, message)
+            }
+            Self::Policy(e) => write!(f, "{}", e),
+            Self::Anthropic(e) => write!(f, "Anthropic API error: {}", e),
+            Self::Llm(message) => write!(f, "language model error: {}", message),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode { source, .. } | Self::InvalidDecision { source, .. } => Some(source),
+            Self::Network { source, .. } => Some(source),
+            Self::Parse(e) => Some(e),
+            Self::ParseWithContext(e) => Some(e),
+            Self::Policy(e) => Some(e),
+            Self::Anthropic(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<ParseErrorWithContext> for Error {
+    fn from(err: ParseErrorWithContext) -> Self {
+        Self::ParseWithContext(err)
+    }
+}
+
+impl From<policyai::ApplyError> for Error {
+    fn from(err: policyai::ApplyError) -> Self {
+        Self::Policy(err)
+    }
+}
+
+impl From<claudius::Error> for Error {
+    fn from(err: claudius::Error) -> Self {
+        Self::Anthropic(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Decode JSON, reporting the path to the first value that does not match `T`
+///
+/// Also returns the names of any fields the models do not know, which only the `lenient` feature
+/// lets through.
+pub fn decode<T: serde::de::DeserializeOwned>(
+    url: &str,
+    body: &[u8],
+) -> Result<(T, Vec<String>), Error> {
+    #[cfg(feature = "lenient")]
+    crate::lenient::take_unknown_fields();
+    let de = &mut serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(de).map_err(|err| Error::Decode {
+        url: url.to_string(),
+        path: err.path().to_string(),
+        source: err.into_inner(),
+    })?;
+    #[cfg(feature = "lenient")]
+    let unknown = crate::lenient::take_unknown_fields();
+    #[cfg(not(feature = "lenient"))]
+    let unknown = Vec::new();
+    Ok((value, unknown))
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_error_parses_github_body() {
+        let body = br#"{"message": "Bad credentials", "documentation_url": "https://docs.github.com/rest"}"#;
+        let err = ApiError::from_body("https://api.github.com/notifications", 401, body);
+        assert_eq!(err.message.as_deref(), Some("Bad credentials"));
+        assert_eq!(
+            err.documentation_url.as_deref(),
+            Some("https://docs.github.com/rest")
+        );
+        assert!(matches!(
+            Error::from_response(err, false),
+            Error::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn api_error_tolerates_empty_body() {
+        let err = ApiError::from_body("https://api.github.com/x", 502, b"");
+        assert_eq!(err.message, None);
+        assert!(matches!(Error::from_response(err, false), Error::Api(_)));
+    }
+
+    #[test]
+    fn classifies_rate_limits() {
+        let secondary = ApiError::from_body(
+            "https://api.github.com/x",
+            403,
+            br#"{"message": "You have exceeded a secondary rate limit."}"#,
+        );
+        assert!(matches!(
+            Error::from_response(secondary, false),
+            Error::RateLimited(_)
+        ));
+        let primary = ApiError::from_body("https://api.github.com/x", 403, b"{}");
+        assert!(matches!(
+            Error::from_response(primary.clone(), true),
+            Error::RateLimited(_)
+        ));
+        assert!(matches!(
+            Error::from_response(primary, false),
+            Error::Forbidden(_)
+        ));
+    }
+
+    #[test]
+    fn decode_reports_path() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Inner {
+            login: String,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Outer {
+            owner: Inner,
+        }
+        let err = decode::<Vec<Outer>>(
+            "u",
+            br#"[{"owner": {"login": "a"}}, {"owner": {"login": 7}}]"#,
+        )
+        .unwrap_err();
+        match err {
+            Error::Decode { url, path, .. } => {
+                assert_eq!(url, "u");
+                assert_eq!(path, "[1].owner.login");
+            }
+            _ => panic!("expected a decode error, got {:?}", err),
+        }
+    }
+}
@@ -1,21 +1,185 @@
-use reqwest::Client;
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
 use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth::discover_token;
+use crate::config::Config;
+use crate::error::{decode, ApiError, Error};
+
+/// The REST API of github.com
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Number of times a throttled or failed request is retried before giving up
+const MAX_RETRIES: u32 = 5;
+/// Base delay for exponential backoff between retries
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// Upper bound on any single backoff delay
+const BACKOFF_CAP: Duration = Duration::from_secs(60);
+/// GitHub asks for at least a minute's wait after a secondary rate limit that gives no other hint
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// A single page of results from a paginated GitHub endpoint
+///
+/// Most endpoints return a bare JSON array, but some (e.g. workflow runs and jobs) wrap the items
+/// in an envelope object.  Implementations unwrap the page into its items.
+pub trait Page: serde::de::DeserializeOwned {
+    type Item;
+
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl<T: serde::de::DeserializeOwned> Page for Vec<T> {
+    type Item = T;
+
+    fn into_items(self) -> Vec<T> {
+        self
+    }
+}
+
+/// The rate-limit budget reported by GitHub on the most recent response
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Requests allowed per window
+    pub limit: Option<u64>,
+    /// Requests left in the current window
+    pub remaining: u64,
+    /// When the current window resets, in seconds since the Unix epoch
+    pub reset: u64,
+}
+
+impl RateLimit {
+    /// Parse the `X-RateLimit-*` headers of a response
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let remaining = header_u64(headers, "x-ratelimit-remaining")?;
+        let reset = header_u64(headers, "x-ratelimit-reset")?;
+        Some(Self {
+            limit: header_u64(headers, "x-ratelimit-limit"),
+            remaining,
+            reset,
+        })
+    }
+
+    /// Time until the window resets, or `None` if it already has
+    pub fn until_reset(&self) -> Option<Duration> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        (self.reset > now).then(|| Duration::from_secs(self.reset - now))
+    }
+}
+
+/// The outcome of [`GitHubClient::paginate_if_modified`]
+#[derive(Clone, Debug)]
+pub struct Conditional<T> {
+    /// Every item, or `None` when the endpoint reported `304 Not Modified`
+    pub items: Option<Vec<T>>,
+    /// The `Last-Modified` to send as `If-Modified-Since` next time
+    pub last_modified: Option<String>,
+    /// How long GitHub asked clients to wait between polls (`X-Poll-Interval`)
+    pub poll_interval: Option<Duration>,
+}
 
 /// GitHub HTTP client with authentication and standard headers
+///
+/// Create one client per run and pass it to every fetcher: clones share the connection pool,
+/// token, configuration, and rate-limit state.
+#[derive(Clone)]
 pub struct GitHubClient {
     client: Client,
     token: String,
+    base_url: String,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    warnings: Arc<Mutex<Vec<String>>>,
 }
 
 impl GitHubClient {
-    /// Create a new GitHub client with authentication from GITHUB_TOKEN environment variable
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let token =
-            env::var("GITHUB_TOKEN").map_err(|_| "GITHUB_TOKEN environment variable not set")?;
-        Ok(Self {
+    /// Create a new GitHub client, discovering its token the way the `gh` CLI does
+    ///
+    /// The API base URL comes from `GHAI_API_URL`, then `GH_HOST`, then the ghai config file,
+    /// defaulting to github.com.  See [`crate::auth`] for where the token is looked for.
+    pub fn new() -> Result<Self, Error> {
+        let config = Config::load()?;
+        let base_url = resolve_api_url(
+            env::var("GHAI_API_URL").ok(),
+            env::var("GH_HOST").ok(),
+            &config,
+        );
+        // The token must be for the host requests go to, so a configured host that `GHAI_API_URL`
+        // or the config's `api_url` overrides is not asked for one.
+        let host = env::var("GH_HOST")
+            .ok()
+            .filter(|h| !h.trim().is_empty())
+            .or_else(|| config.host.clone())
+            .filter(|h| api_url_for_host(h) == base_url)
+            .unwrap_or_else(|| host_for_api_url(&base_url));
+        let token = discover_token(&host, &config)?;
+        Ok(Self::with_token(token).with_base_url(base_url))
+    }
+
+    /// Create a client for github.com with an explicit token, bypassing discovery
+    pub fn with_token(token: impl Into<String>) -> Self {
+        Self {
             client: Client::new(),
-            token,
-        })
+            token: token.into(),
+            base_url: DEFAULT_API_URL.to_string(),
+            rate_limit: Arc::new(Mutex::new(None)),
+            warnings: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Point this client at a different API base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The base URL every API path is resolved against
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Resolve an API path such as `/notifications` against the base URL
+    pub fn api_url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// The GraphQL endpoint that goes with the REST base URL
+    ///
+    /// GitHub Enterprise serves REST under `/api/v3` and GraphQL at `/api/graphql`; elsewhere it
+    /// lives at `/graphql` under the base URL.
+    pub fn graphql_url(&self) -> String {
+        match self.base_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => self.api_url("/graphql"),
+        }
+    }
+
+    /// The rate-limit budget as of the most recent response, if any has been seen
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Drain the problems noticed since the last call that did not fail a request, such as
+    /// fields the `lenient` feature let through
+    ///
+    /// The client never prints them; it is up to the program whether and where to show them.
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+
+    async fn wait_for_budget(&self) {
+        if let Some(rate_limit) = self.rate_limit().filter(|rl| rl.remaining == 0) {
+            if let Some(delay) = rate_limit.until_reset() {
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+
+    fn record_rate_limit(&self, headers: &HeaderMap) {
+        if let Some(rate_limit) = RateLimit::from_headers(headers) {
+            *self.rate_limit.lock().unwrap() = Some(rate_limit);
+        }
     }
 
     /// Create a GET request with standard GitHub headers
@@ -29,13 +193,406 @@ impl GitHubClient {
     }
 
     /// Create a request with the specified method and standard GitHub headers
+    ///
+    /// The token is only sent to the origin of the base URL; a `Link` or subject URL that names
+    /// another host is requested without it.
     pub fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
-        self.client
+        let request = self
+            .client
             .request(method, url)
-            .header("Authorization", format!("Bearer {}", self.token))
             .header("Accept", "application/vnd.github+json")
-            .header("User-Agent", "ghai")
+            .header("User-Agent", "ghai");
+        if same_origin(url, &self.base_url) {
+            request.header("Authorization", format!("Bearer {}", self.token))
+        } else {
+            request
+        }
+    }
+
+    /// Send a request, waiting out exhausted rate limits and retrying transient failures
+    ///
+    /// When the last known budget is exhausted the request is held until the window resets.
+    /// Server errors, secondary rate limits (403/429 with `Retry-After`, an exhausted budget, or a
+    /// message saying so), and connection failures are retried with jittered exponential backoff.
+    /// Any other response is returned as-is for the caller to interpret; see
+    /// [`GitHubClient::execute`] for a variant that turns error statuses into [`Error`]s.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_budget().await;
+            // A request with a streaming body cannot be replayed, so it gets exactly one attempt.
+            let Some(req) = request.try_clone() else {
+                let url = request_url(&request);
+                let response = request.send().await.map_err(|e| Error::network(url, e))?;
+                self.record_rate_limit(response.headers());
+                return Ok(response);
+            };
+            let url = request_url(&req);
+            match req.send().await {
+                Ok(response) => {
+                    self.record_rate_limit(response.headers());
+                    let (response, delay) =
+                        match retry_delay(response.status(), response.headers(), attempt) {
+                            None if response.status() == StatusCode::FORBIDDEN => {
+                                secondary_rate_limit(response, attempt).await?
+                            }
+                            delay => (response, delay),
+                        };
+                    match delay {
+                        Some(delay) if attempt < MAX_RETRIES => {
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                        }
+                        _ => return Ok(response),
+                    }
+                }
+                Err(err) if (err.is_connect() || err.is_timeout()) && attempt < MAX_RETRIES => {
+                    tokio::time::sleep(backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(Error::network(url, err)),
+            }
+        }
+    }
+
+    /// Send a request like [`GitHubClient::send`], turning error statuses into an [`Error`]
+    pub async fn execute(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        error_for_status(self.send(request).await?).await
+    }
+
+    /// GET `url` and decode its JSON body as `T`
+    pub async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        self.json(self.execute(self.get(url)).await?).await
+    }
+
+    /// Run a GraphQL query and decode its `data` as `T`
+    ///
+    /// GraphQL reports most failures in an `errors` array of a successful response; those become
+    /// [`Error::GraphQl`], even when some data came back.
+    pub async fn graphql<T: serde::de::DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, Error> {
+        #[derive(serde::Deserialize)]
+        struct Response<T> {
+            data: Option<T>,
+            #[serde(default)]
+            errors: Vec<Message>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Message {
+            message: String,
+        }
+        let url = self.graphql_url();
+        let request = self.post(&url).json(&serde_json::json!({
+            "query": query,
+            "variables": variables,
+        }));
+        let response: Response<T> = self.json(self.execute(request).await?).await?;
+        match response.data {
+            Some(data) if response.errors.is_empty() => Ok(data),
+            data => Err(Error::GraphQl {
+                url,
+                messages: if response.errors.is_empty() && data.is_none() {
+                    vec!["the response has no data".to_string()]
+                } else {
+                    response.errors.into_iter().map(|e| e.message).collect()
+                },
+            }),
+        }
+    }
+
+    /// Read a response body and decode it as JSON, reporting the path of any mismatch
+    ///
+    /// Unknown fields the `lenient` feature lets through are kept for [`Self::take_warnings`].
+    pub async fn json<T: serde::de::DeserializeOwned>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<T, Error> {
+        let url = response.url().to_string();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| Error::network(url.clone(), e))?;
+        let (value, unknown) = decode(&url, &body)?;
+        if !unknown.is_empty() {
+            self.warnings.lock().unwrap().push(format!(
+                "Ignoring unknown fields in response from {}: {}",
+                url,
+                unknown.join(", ")
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Fetch every page of a paginated endpoint, following the `Link: rel="next"` header
+    ///
+    /// At most `max_items` items are returned when it is set; no further pages are requested
+    /// once the cap is reached.
+    pub async fn paginate<P: Page>(
+        &self,
+        url: &str,
+        max_items: Option<usize>,
+    ) -> Result<Vec<P::Item>, Error> {
+        self.paginate_stream::<P>(url, max_items)
+            .try_collect()
+            .await
+    }
+
+    /// Fetch every page of a paginated endpoint unless it is unchanged since `if_modified_since`
+    ///
+    /// The first page is requested with `If-Modified-Since`; a `304 Not Modified` answer costs no
+    /// rate-limit budget and yields no items.  Pass the returned `last_modified` to the next call.
+    pub async fn paginate_if_modified<P: Page>(
+        &self,
+        url: &str,
+        if_modified_since: Option<&str>,
+    ) -> Result<Conditional<P::Item>, Error> {
+        let mut request = self.get(url);
+        if let Some(since) = if_modified_since {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, since);
+        }
+        let response = self.send(request).await?;
+        let headers = response.headers();
+        let last_modified = headers
+            .get(reqwest::header::LAST_MODIFIED)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+            .or_else(|| if_modified_since.map(String::from));
+        let poll_interval = header_u64(headers, "x-poll-interval").map(Duration::from_secs);
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional {
+                items: None,
+                last_modified,
+                poll_interval,
+            });
+        }
+        let response = error_for_status(response).await?;
+        let next = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(next_link);
+        let page: P = self.json(response).await?;
+        let mut items = page.into_items();
+        if let Some(next) = next {
+            items.extend(self.paginate::<P>(&next, None).await?);
+        }
+        Ok(Conditional {
+            items: Some(items),
+            last_modified,
+            poll_interval,
+        })
+    }
+
+    /// Stream the items of a paginated endpoint, fetching each page only as it is needed
+    pub fn paginate_stream<'a, P: Page + 'a>(
+        &'a self,
+        url: &str,
+        max_items: Option<usize>,
+    ) -> impl Stream<Item = Result<P::Item, Error>> + 'a {
+        let pages = futures::stream::try_unfold(Some(url.to_string()), move |url| async move {
+            let Some(url) = url else {
+                return Ok::<_, Error>(None);
+            };
+            let response = self.execute(self.get(&url)).await?;
+            let next = response
+                .headers()
+                .get(reqwest::header::LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_link);
+            let page: P = self.json(response).await?;
+            Ok(Some((page.into_items(), next)))
+        });
+        pages
+            .map_ok(|items| futures::stream::iter(items.into_iter().map(Ok)))
+            .try_flatten()
+            .take(max_items.unwrap_or(usize::MAX))
+    }
+}
+
+/// Turn a non-success response into the matching [`Error`], reading GitHub's error body
+pub async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        return Ok(response);
     }
+    let url = response.url().to_string();
+    let exhausted = RateLimit::from_headers(response.headers()).is_some_and(|rl| rl.remaining == 0);
+    let body = response.bytes().await.unwrap_or_default();
+    Err(Error::from_response(
+        ApiError::from_body(url, status.as_u16(), &body),
+        exhausted,
+    ))
+}
+
+fn request_url(request: &reqwest::RequestBuilder) -> String {
+    request
+        .try_clone()
+        .and_then(|r| r.build().ok())
+        .map(|r| r.url().to_string())
+        .unwrap_or_default()
+}
+
+/// Pick the API base URL from `GHAI_API_URL`, `GH_HOST`, or the config file, in that order
+pub fn resolve_api_url(
+    ghai_api_url: Option<String>,
+    gh_host: Option<String>,
+    config: &Config,
+) -> String {
+    let url = ghai_api_url
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| {
+            gh_host
+                .filter(|h| !h.trim().is_empty())
+                .map(|h| api_url_for_host(&h))
+        })
+        .or_else(|| config.api_url.clone())
+        .or_else(|| config.host.as_deref().map(api_url_for_host))
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+    url.trim_end_matches('/').to_string()
+}
+
+/// The REST API URL of a GitHub host: api.github.com for github.com, `/api/v3` for Enterprise
+pub fn api_url_for_host(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host);
+    if host == "github.com" || host == "api.github.com" {
+        DEFAULT_API_URL.to_string()
+    } else {
+        format!("https://{host}/api/v3")
+    }
+}
+
+/// The host a REST API URL belongs to, for looking up credentials
+pub fn host_for_api_url(api_url: &str) -> String {
+    let rest = api_url
+        .strip_prefix("https://")
+        .or_else(|| api_url.strip_prefix("http://"))
+        .unwrap_or(api_url);
+    let host = rest.split('/').next().unwrap_or(rest);
+    if host == "api.github.com" {
+        "github.com".to_string()
+    } else {
+        host.to_string()
+    }
+}
+
+/// Whether two URLs share a scheme, host, and port
+fn same_origin(a: &str, b: &str) -> bool {
+    match (reqwest::Url::parse(a), reqwest::Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// How long to wait before retrying a response, or `None` if it should not be retried
+fn retry_delay(status: StatusCode, headers: &HeaderMap, attempt: u32) -> Option<Duration> {
+    let throttled = status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::FORBIDDEN;
+    if throttled {
+        if let Some(retry_after) = retry_after(headers) {
+            return Some(retry_after);
+        }
+        if let Some(rate_limit) = RateLimit::from_headers(headers).filter(|rl| rl.remaining == 0) {
+            return Some(rate_limit.until_reset().unwrap_or_else(|| backoff(attempt)));
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Some(backoff(attempt));
+        }
+    }
+    status.is_server_error().then(|| backoff(attempt))
+}
+
+/// Read a 403 that no header marks as throttled, and how long to wait if its message says it is a
+/// secondary rate limit
+///
+/// The body has to be read to tell, so the response is rebuilt around it for the caller.
+async fn secondary_rate_limit(
+    response: reqwest::Response,
+    attempt: u32,
+) -> Result<(reqwest::Response, Option<Duration>), Error> {
+    use reqwest::ResponseBuilderExt;
+
+    let url = response.url().clone();
+    let status = response.status();
+    let mut rebuilt = http::Response::builder()
+        .status(status)
+        .version(response.version())
+        .url(url.clone());
+    if let Some(headers) = rebuilt.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| Error::network(url.to_string(), e))?;
+    let delay = ApiError::from_body(url.as_str(), status.as_u16(), &body)
+        .is_rate_limit()
+        .then(|| SECONDARY_RATE_LIMIT_WAIT + backoff(attempt));
+    let rebuilt = rebuilt
+        .body(body)
+        .expect("the parts of a received response are valid");
+    Ok((reqwest::Response::from(rebuilt), delay))
+}
+
+/// Exponential backoff with "equal jitter": half the delay is fixed, half is random
+fn backoff(attempt: u32) -> Duration {
+    let delay = BACKOFF_BASE
+        .saturating_mul(1 << attempt.min(16))
+        .min(BACKOFF_CAP);
+    delay / 2 + delay.mul_f64(rand::random::<f64>() / 2.0)
+}
+
+/// The wait a `Retry-After` header asks for, given either in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(seconds) = header_u64(headers, "retry-after") {
+        return Some(Duration::from_secs(seconds));
+    }
+    let value = headers.get("retry-after")?.to_str().ok()?;
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Extract the target of the `rel="next"` entry from a GitHub `Link` header
+pub fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let mut parts = entry.split(';');
+        let target = parts.next()?.trim();
+        let is_next = parts.any(|param| {
+            param
+                .trim()
+                .strip_prefix("rel=")
+                .map(|rel| {
+                    rel.trim_matches('"')
+                        .split_whitespace()
+                        .any(|r| r == "next")
+                })
+                .unwrap_or(false)
+        });
+        if is_next {
+            target
+                .strip_prefix('<')
+                .and_then(|t| t.strip_suffix('>'))
+                .map(String::from)
+        } else {
+            None
+        }
+    })
 }
 
 /// URL builder utility for constructing URLs with query parameters
@@ -112,4 +669,200 @@ mod tests {
             "https://api.github.com/search?q=repo%3Auser%2Frepo%20is%3Aissue"
         );
     }
+
+    #[test]
+    fn api_url_for_hosts() {
+        assert_eq!(api_url_for_host("github.com"), "https://api.github.com");
+        assert_eq!(
+            api_url_for_host("github.example.com"),
+            "https://github.example.com/api/v3"
+        );
+        assert_eq!(
+            api_url_for_host("https://github.example.com/"),
+            "https://github.example.com/api/v3"
+        );
+    }
+
+    #[test]
+    fn resolve_api_url_precedence() {
+        let config = Config {
+            api_url: Some("http://config.example/".to_string()),
+            host: Some("config-host.example".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            resolve_api_url(
+                Some("http://127.0.0.1:8080/".to_string()),
+                Some("ghe.example".to_string()),
+                &config
+            ),
+            "http://127.0.0.1:8080"
+        );
+        assert_eq!(
+            resolve_api_url(None, Some("ghe.example".to_string()), &config),
+            "https://ghe.example/api/v3"
+        );
+        assert_eq!(
+            resolve_api_url(None, None, &config),
+            "http://config.example"
+        );
+        let config = Config {
+            host: Some("config-host.example".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            resolve_api_url(None, None, &config),
+            "https://config-host.example/api/v3"
+        );
+        assert_eq!(
+            resolve_api_url(None, None, &Config::default()),
+            DEFAULT_API_URL
+        );
+    }
+
+    #[test]
+    fn host_for_api_urls() {
+        assert_eq!(host_for_api_url(DEFAULT_API_URL), "github.com");
+        assert_eq!(
+            host_for_api_url("https://github.example.com/api/v3"),
+            "github.example.com"
+        );
+        assert_eq!(host_for_api_url("http://127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn origins() {
+        assert!(same_origin(
+            "https://api.github.com/notifications?page=2",
+            DEFAULT_API_URL
+        ));
+        assert!(same_origin(
+            "https://github.example.com/api/v3/issues",
+            "https://github.example.com/api/v3"
+        ));
+        assert!(!same_origin(
+            "https://evil.example/notifications",
+            DEFAULT_API_URL
+        ));
+        assert!(!same_origin(
+            "http://api.github.com/notifications",
+            DEFAULT_API_URL
+        ));
+        assert!(!same_origin(
+            "http://127.0.0.1:8081/x",
+            "http://127.0.0.1:8080"
+        ));
+        assert!(!same_origin("/notifications", DEFAULT_API_URL));
+    }
+
+    #[test]
+    fn graphql_urls() {
+        let client = GitHubClient::with_token("t");
+        assert_eq!(client.graphql_url(), "https://api.github.com/graphql");
+        let client = client.with_base_url("https://github.example.com/api/v3");
+        assert_eq!(
+            client.graphql_url(),
+            "https://github.example.com/api/graphql"
+        );
+        let client = client.with_base_url("http://127.0.0.1:8080/");
+        assert_eq!(client.graphql_url(), "http://127.0.0.1:8080/graphql");
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn rate_limit_from_headers() {
+        let rl = RateLimit::from_headers(&headers(&[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "4999"),
+            ("x-ratelimit-reset", "1700000000"),
+        ]));
+        assert_eq!(
+            rl,
+            Some(RateLimit {
+                limit: Some(5000),
+                remaining: 4999,
+                reset: 1700000000,
+            })
+        );
+        assert_eq!(RateLimit::from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_delay_honors_retry_after() {
+        let h = headers(&[("retry-after", "7")]);
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &h, 0),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            retry_delay(StatusCode::TOO_MANY_REQUESTS, &h, 3),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn retry_delay_honors_retry_after_dates() {
+        let later = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let h = headers(&[("retry-after", &later.to_rfc2822().replace("+0000", "GMT"))]);
+        let delay = retry_delay(StatusCode::TOO_MANY_REQUESTS, &h, 0).unwrap();
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90));
+        let h = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &h, 0),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_delay_classifies_statuses() {
+        let empty = HeaderMap::new();
+        assert!(retry_delay(StatusCode::BAD_GATEWAY, &empty, 0).is_some());
+        assert!(retry_delay(StatusCode::TOO_MANY_REQUESTS, &empty, 0).is_some());
+        assert!(retry_delay(StatusCode::FORBIDDEN, &empty, 0).is_none());
+        assert!(retry_delay(StatusCode::NOT_FOUND, &empty, 0).is_none());
+        assert!(retry_delay(StatusCode::OK, &empty, 0).is_none());
+    }
+
+    #[test]
+    fn backoff_is_bounded() {
+        for attempt in 0..32 {
+            let delay = backoff(attempt);
+            let ceiling = BACKOFF_BASE
+                .saturating_mul(1 << attempt.min(16))
+                .min(BACKOFF_CAP);
+            assert!(delay >= ceiling / 2 && delay <= ceiling);
+        }
+    }
+
+    #[test]
+    fn next_link_finds_next() {
+        let link = r#"<https://api.github.com/notifications?page=2>; rel="next", <https://api.github.com/notifications?page=5>; rel="last""#;
+        assert_eq!(
+            next_link(link),
+            Some("https://api.github.com/notifications?page=2".to_string())
+        );
+    }
+
+    #[test]
+    fn next_link_not_first_entry() {
+        let link = r#"<https://api.github.com/issues?page=1>; rel="prev", <https://api.github.com/issues?page=3>; rel="next""#;
+        assert_eq!(
+            next_link(link),
+            Some("https://api.github.com/issues?page=3".to_string())
+        );
+    }
+
+    #[test]
+    fn next_link_on_last_page() {
+        let link = r#"<https://api.github.com/issues?page=1>; rel="first", <https://api.github.com/issues?page=2>; rel="prev""#;
+        assert_eq!(next_link(link), None);
+        assert_eq!(next_link(""), None);
+    }
 }
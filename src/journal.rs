@@ -0,0 +1,296 @@
+//! A journal of the changes each processor run makes to GitHub, so that a run can be undone.
+//!
+//! A run that changes anything is journaled to `$XDG_STATE_HOME/ghai/journal/<run id>.jsonl`
+//! (falling back to `~/.local/state`), one line per change, appended as soon as the change
+//! succeeds and carrying the notification it was made to.  Run ids start with the time the run
+//! began, so they sort oldest first.  Undoing a run renames its journal to `<run id>.undone`.
+//!
+//! GitHub has no way to mark a thread unread again, so only subscriptions, stars, and labels can
+//! be restored; the journal still records reads so that the threads can be listed.
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::config::state_home;
+use crate::error::Error;
+use crate::types::Notification;
+
+/////////////////////////////////////////////// Change /////////////////////////////////////////////
+
+/// One change a run made to GitHub
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum Change {
+    MarkRead,
+    MarkDone,
+    /// Unsubscribed from the thread and marked it read
+    Unsubscribe,
+    /// Ignored the thread and marked it read
+    Ignore,
+    Star,
+    /// Added these labels, which the issue or pull request did not already have
+    Label {
+        labels: Vec<String>,
+    },
+}
+
+impl Change {
+    /// Whether the change marked the thread read, which cannot be undone
+    pub fn marks_read(&self) -> bool {
+        matches!(
+            self,
+            Change::MarkRead | Change::MarkDone | Change::Unsubscribe | Change::Ignore
+        )
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::MarkRead => f.write_str("mark-read"),
+            Change::MarkDone => f.write_str("mark-done"),
+            Change::Unsubscribe => f.write_str("unsubscribe"),
+            Change::Ignore => f.write_str("ignore"),
+            Change::Star => f.write_str("star"),
+            Change::Label { labels } => write!(f, "label {}", labels.join(", ")),
+        }
+    }
+}
+
+//////////////////////////////////////////// JournalEntry //////////////////////////////////////////
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: Change,
+    /// The notification as it was when the change was made
+    pub thread: Notification,
+}
+
+////////////////////////////////////////////// Journal /////////////////////////////////////////////
+
+pub struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    /// The journal in its default location
+    pub fn open() -> Result<Self, Error> {
+        let dir = state_home().ok_or_else(|| {
+            Error::Config("cannot find a state directory; set XDG_STATE_HOME or HOME".to_string())
+        })?;
+        Ok(Self::at(dir.join("ghai").join("journal")))
+    }
+
+    /// A journal stored in `dir`
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Start journaling a new run
+    pub fn start_run(&self) -> Run {
+        let id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            std::process::id()
+        );
+        Run {
+            path: self.path(&id),
+            id,
+            changes: 0,
+        }
+    }
+
+    /// The ids of the runs that have not been undone, oldest first
+    pub fn runs(&self) -> Result<Vec<String>, Error> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut runs = Vec::new();
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                if let Some(stem) = path.file_stem() {
+                    runs.push(stem.to_string_lossy().to_string());
+                }
+            }
+        }
+        runs.sort();
+        Ok(runs)
+    }
+
+    /// The changes `run_id` made, in the order it made them
+    pub fn entries(&self, run_id: &str) -> Result<Vec<JournalEntry>, Error> {
+        let path = self.checked_path(run_id)?;
+        let file = std::fs::File::open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::Config(format!(
+                "no journal for run {} that has not been undone",
+                run_id
+            )),
+            _ => e.into(),
+        })?;
+        let mut entries = Vec::new();
+        for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path.display(), index + 1, e),
+                )
+            })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Set a run aside once it has been undone, so it is neither listed nor undone twice
+    pub fn mark_undone(&self, run_id: &str) -> Result<(), Error> {
+        let path = self.checked_path(run_id)?;
+        std::fs::rename(&path, path.with_extension("undone"))?;
+        Ok(())
+    }
+
+    fn path(&self, run_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", run_id))
+    }
+
+    /// The path for a run id given by the user, which must not wander out of the journal
+    fn checked_path(&self, run_id: &str) -> Result<PathBuf, Error> {
+        if run_id.is_empty()
+            || !run_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(Error::Config(format!("invalid run id '{}'", run_id)));
+        }
+        Ok(self.path(run_id))
+    }
+}
+
+//////////////////////////////////////////////// Run ///////////////////////////////////////////////
+
+/// The journal of one run; nothing is written until the first change
+pub struct Run {
+    id: String,
+    path: PathBuf,
+    changes: usize,
+}
+
+impl Run {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The number of changes recorded so far
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Append a change that has just been made to `thread`
+    pub fn record(&mut self, thread: &Notification, change: Change) -> Result<(), Error> {
+        let entry = JournalEntry {
+            timestamp: Utc::now(),
+            change,
+            thread: thread.clone(),
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(&entry).expect("entries serialize");
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        self.changes += 1;
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_thread;
+
+    #[test]
+    fn runs_record_and_undo() {
+        let dir = std::env::temp_dir().join(format!("ghai-journal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let journal = Journal::at(&dir);
+        assert!(journal.runs().unwrap().is_empty());
+
+        let mut run = journal.start_run();
+        // A run that changes nothing leaves nothing behind.
+        assert!(journal.runs().unwrap().is_empty());
+        let thread = fixture_thread("notifications_page1.json", 0);
+        run.record(&thread, Change::Unsubscribe).unwrap();
+        run.record(
+            &thread,
+            Change::Label {
+                labels: vec!["triaged".to_string()],
+            },
+        )
+        .unwrap();
+        assert_eq!(2, run.changes());
+        assert_eq!(vec![run.id().to_string()], journal.runs().unwrap());
+
+        let entries = journal.entries(run.id()).unwrap();
+        let changes = entries.iter().map(|e| e.change.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Change::Unsubscribe,
+                Change::Label {
+                    labels: vec!["triaged".to_string()]
+                }
+            ],
+            changes
+        );
+        assert_eq!("101", entries[0].thread.id);
+        assert!(entries[0].change.marks_read());
+        assert!(!entries[1].change.marks_read());
+
+        journal.mark_undone(run.id()).unwrap();
+        assert!(journal.runs().unwrap().is_empty());
+        assert!(matches!(journal.entries(run.id()), Err(Error::Config(_))));
+        assert!(matches!(journal.entries("../audit"), Err(Error::Config(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn change_format() {
+        let value = serde_json::to_value(Change::Label {
+            labels: vec!["a".to_string()],
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::json!({"change": "label", "labels": ["a"]}),
+            value
+        );
+        assert_eq!(
+            serde_json::json!({"change": "mark-done"}),
+            serde_json::to_value(Change::MarkDone).unwrap()
+        );
+        assert_eq!(
+            "label a, b",
+            Change::Label {
+                labels: vec!["a".to_string(), "b".to_string()]
+            }
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Lenient decoding of GitHub responses.
+//!
+//! With the `lenient` feature every model in [`crate::types`] tolerates fields it does not know
+//! about: they are kept in the model's `extra` bucket and left as a warning for
+//! [`crate::http::GitHubClient::take_warnings`] instead of failing the decode.  Without the
+//! feature the models deny unknown fields, which is what the tests want.
+
+use std::cell::RefCell;
+
+thread_local! {
+    static UNKNOWN_FIELDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+////////////////////////////////////////////// Extra ///////////////////////////////////////////////
+
+/// Fields of a GitHub response that the model does not declare
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(transparent)]
+pub struct Extra(pub serde_json::Map<String, serde_json::Value>);
+
+impl Extra {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.0.get(key)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Extra {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        if !fields.is_empty() {
+            UNKNOWN_FIELDS.with(|unknown| unknown.borrow_mut().extend(fields.keys().cloned()));
+        }
+        Ok(Self(fields))
+    }
+}
+
+/// Drain the names of the unknown fields seen on this thread since the last call
+pub fn take_unknown_fields() -> Vec<String> {
+    let mut fields = UNKNOWN_FIELDS.with(|unknown| std::mem::take(&mut *unknown.borrow_mut()));
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RepositoryLicense;
+
+    #[test]
+    fn unknown_fields_land_in_extra() {
+        take_unknown_fields();
+        let license: RepositoryLicense = serde_json::from_value(serde_json::json!({
+            "key": "apache-2.0",
+            "name": "Apache License 2.0",
+            "spdx_id": "Apache-2.0",
+            "url": null,
+            "node_id": "MDc6TGljZW5zZTI=",
+            "brand_new_field": true,
+        }))
+        .unwrap();
+        assert_eq!(license.key, "apache-2.0");
+        assert_eq!(
+            license.extra.get("brand_new_field"),
+            Some(&serde_json::Value::Bool(true))
+        );
+        assert_eq!(take_unknown_fields(), vec!["brand_new_field".to_string()]);
+        assert!(take_unknown_fields().is_empty());
+    }
+
+    #[test]
+    fn extra_round_trips() {
+        let value = serde_json::json!({
+            "key": "mit",
+            "name": "MIT License",
+            "spdx_id": "MIT",
+            "url": null,
+            "node_id": "MDc6TGljZW5zZTEz",
+            "added_later": "yes",
+        });
+        let license: RepositoryLicense = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&license).unwrap(), value);
+    }
+}
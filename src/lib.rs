@@ -1,8 +1,96 @@
+/// Define a `#[non_exhaustive]` enum over one of GitHub's string vocabularies
+///
+/// Each variant maps to its wire string; any other string decodes to `Unknown` and round-trips
+/// unchanged, so a value GitHub adds later does not break decoding.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Deserialize, serde::Serialize)]
+        #[serde(from = "String", into = "String")]
+        #[non_exhaustive]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// A value this version of ghai does not know about
+            Unknown(String),
+        }
+
+        impl $name {
+            /// The string GitHub uses for this value
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match value.as_str() {
+                    $($value => Self::$variant,)*
+                    _ => Self::Unknown(value),
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self::from(value.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Unknown(value) => value,
+                    known => known.as_str().to_string(),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+mod error;
 mod types;
 
+pub mod audit;
+pub mod auth;
+pub mod budget;
+pub mod cache;
+pub mod config;
 pub mod http;
+pub mod journal;
+#[cfg(feature = "lenient")]
+pub mod lenient;
+pub mod llm;
 pub mod parser;
 pub mod policy;
+pub mod queue;
+pub mod rules;
+pub mod subject;
 pub mod xml;
 
+pub use error::{ApiError, Error};
 pub use types::*;
+
+/// Thread `index` of the notifications fixture `file` under `tests/fixtures`
+#[cfg(test)]
+pub(crate) fn fixture_thread(file: &str, index: usize) -> Notification {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(file);
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    serde_json::from_value(value[index].clone()).unwrap()
+}
@@ -0,0 +1,1198 @@
+//! Language model backends.
+//!
+//! Every backend speaks the Anthropic Messages shape: a [`MessageCreateParams`] goes in and a
+//! [`Message`] comes out.  That keeps `policyai`'s request building usable everywhere while
+//! letting triage run on Anthropic, on any OpenAI-compatible server (including a local Ollama),
+//! or on a [`ScriptedLlm`] or [`KeywordLlm`] in tests.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use claudius::{
+    Anthropic, ContentBlock, ContentBlockDelta, Message, MessageCreateParams, MessageParam,
+    MessageParamContent, MessageRole, MessageStreamEvent, Model, StopReason, SystemPrompt,
+    TextBlock, ToolChoice, ToolResultBlock, ToolResultBlockContent, ToolUnionParam, ToolUseBlock,
+};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use policyai::{ApplyError, Field, Manager, Policy, Report, ReportBuilder, Usage};
+
+use crate::config::LlmConfig;
+use crate::error::{decode, Error};
+use crate::policy::Explanation;
+
+/// The OpenAI API
+pub const OPENAI_URL: &str = "https://api.openai.com/v1";
+/// Ollama's OpenAI-compatible API on its default port
+pub const OLLAMA_URL: &str = "http://localhost:11434/v1";
+
+/// Times the model is asked to correct inconsistent policy output before giving up
+const MAX_POLICY_ATTEMPTS: usize = 5;
+/// Name of the tool used to force structured output
+const OUTPUT_TOOL: &str = "output_json";
+/// Property of the policy output in which the model explains itself
+const RATIONALE: &str = "__rationale__";
+
+//////////////////////////////////////////////// Llm ///////////////////////////////////////////////
+
+/// A language model that can answer Anthropic-style message requests
+///
+/// The model in each request is ignored; backends always use [`Llm::model`].
+pub trait Llm: Send + Sync {
+    /// The model requests are sent to
+    fn model(&self) -> &str;
+
+    /// Send a request and wait for the complete response
+    fn send(&self, req: MessageCreateParams) -> BoxFuture<'_, Result<Message, Error>>;
+
+    /// Send a request and stream the text of the response as it is generated
+    fn stream(
+        &self,
+        req: MessageCreateParams,
+    ) -> BoxFuture<'_, Result<BoxStream<'_, Result<String, Error>>, Error>>;
+}
+
+/// The text of a response, with every text block concatenated
+pub fn text(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Send a request and return the text of the response
+pub async fn complete(llm: &dyn Llm, req: MessageCreateParams) -> Result<String, Error> {
+    Ok(text(&llm.send(req).await?))
+}
+
+/// Send a request that must be answered with JSON matching `schema`
+///
+/// The model is forced to call a single tool whose input schema is `schema`, and that input is
+/// returned.
+pub async fn structured(
+    llm: &dyn Llm,
+    mut req: MessageCreateParams,
+    schema: serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    force_output_tool(&mut req, schema);
+    let resp = llm.send(req).await?;
+    tool_use(&resp)
+        .map(|t| t.input.clone())
+        .ok_or_else(|| Error::Llm(format!("{} did not call {}", llm.model(), OUTPUT_TOOL)))
+}
+
+fn force_output_tool(req: &mut MessageCreateParams, schema: serde_json::Value) {
+    let mut tool = claudius::ToolParam::new(OUTPUT_TOOL.to_string(), schema);
+    tool.description = Some("output JSON".to_string());
+    req.tools = Some(vec![ToolUnionParam::CustomTool(tool)]);
+    req.tool_choice = Some(ToolChoice::tool(OUTPUT_TOOL));
+}
+
+fn tool_use(message: &Message) -> Option<&ToolUseBlock> {
+    message.content.iter().find_map(|block| match block {
+        ContentBlock::ToolUse(t) => Some(t),
+        _ => None,
+    })
+}
+
+/// Apply the manager's policies to `text` using any backend
+///
+/// This follows `policyai::Manager::apply`: the model reports the rules it matched alongside its
+/// output, and when the two disagree it is told what is inconsistent and asked again.
+pub async fn apply_policies(
+    llm: &dyn Llm,
+    manager: &mut Manager,
+    template: MessageCreateParams,
+    text: &str,
+    mut usage: Option<&mut Usage>,
+) -> Result<Report, Error> {
+    let start = Instant::now();
+    let (builder, mut req) = manager.request_for(template, text).await?;
+    ask_for_rationale(&mut req);
+    if let Some(usage) = usage.as_deref_mut() {
+        *usage = Usage::new();
+    }
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_POLICY_ATTEMPTS {
+        let resp = llm.send(req.clone()).await?;
+        if let Some(usage) = usage.as_deref_mut() {
+            usage.add_claudius_usage(resp.usage);
+            usage.increment_iterations();
+        }
+        let Some(tool_use) = tool_use(&resp).cloned() else {
+            return Err(Error::Llm(format!(
+                "{} answered without calling {}",
+                llm.model(),
+                OUTPUT_TOOL
+            )));
+        };
+        let reported = tool_use
+            .input
+            .get("__rule_numbers__")
+            .cloned()
+            .and_then(|v| serde_json::from_value::<Vec<usize>>(v).ok());
+        let feedback = match reported {
+            Some(mut reported) => {
+                let mut report = builder.clone().consume_ir(tool_use.input.clone())?;
+                let mut matched = report.rules_matched.clone();
+                matched.sort();
+                matched.dedup();
+                reported.sort();
+                reported.dedup();
+                if matched == reported {
+                    if let Some(usage) = usage.as_deref_mut() {
+                        usage.set_wall_clock_time(start.elapsed());
+                    }
+                    report.ir = Some(tool_use.input);
+                    return Ok(report);
+                }
+                last_error = format!(
+                    "attempt {attempt}/{MAX_POLICY_ATTEMPTS}: output matched rules {matched:?} but reported {reported:?}"
+                );
+                inconsistency(&report, &matched, &reported)
+            }
+            None => {
+                last_error = format!(
+                    "attempt {attempt}/{MAX_POLICY_ATTEMPTS}: missing \"__rule_numbers__\""
+                );
+                "Your output must include \"__rule_numbers__\", the list of rules that matched."
+                    .to_string()
+            }
+        };
+        req.messages.push(MessageParam::new(
+            MessageParamContent::Array(resp.content.clone()),
+            MessageRole::Assistant,
+        ));
+        let mut result = ToolResultBlock::new(tool_use.id.clone());
+        result.is_error = Some(true);
+        result.content = Some(ToolResultBlockContent::String(format!(
+            "<error-message>{feedback}</error-message>"
+        )));
+        req.messages.push(MessageParam::new_with_blocks(
+            vec![ContentBlock::ToolResult(result)],
+            MessageRole::User,
+        ));
+    }
+    if let Some(usage) = usage {
+        usage.set_wall_clock_time(start.elapsed());
+    }
+    Err(policyai::ApplyError::too_many_iterations(MAX_POLICY_ATTEMPTS, last_error).into())
+}
+
+/// Add an optional property to the output tool in which the model says why it decided as it did
+///
+/// Masks only look at the properties they generated, so the extra property does not disturb
+/// matching.
+fn ask_for_rationale(req: &mut MessageCreateParams) {
+    for tool in req.tools.iter_mut().flatten() {
+        if let ToolUnionParam::CustomTool(tool) = tool {
+            if let Some(properties) = tool.input_schema.get_mut("properties") {
+                properties[RATIONALE] = serde_json::json!({
+                    "type": "string",
+                    "description": "One or two sentences on why the reported rules matched, or why none did",
+                });
+            }
+        }
+    }
+}
+
+/// The policies that matched in `report` and the model's rationale, if it gave one
+///
+/// `prompts` are the prompts of the policies the report was made with, in order.
+pub fn explain(report: &Report, prompts: &[String]) -> Explanation {
+    let mut matched = report.rules_matched.clone();
+    matched.sort();
+    matched.dedup();
+    Explanation {
+        policies: matched
+            .into_iter()
+            .filter_map(|rule| rule.checked_sub(1).and_then(|index| prompts.get(index)))
+            .cloned()
+            .collect(),
+        rationale: report
+            .ir
+            .as_ref()
+            .and_then(|ir| ir.get(RATIONALE))
+            .and_then(serde_json::Value::as_str)
+            .map(str::trim)
+            .filter(|rationale| !rationale.is_empty())
+            .map(String::from),
+    }
+}
+
+fn inconsistency(report: &Report, matched: &[usize], reported: &[usize]) -> String {
+    let mut content =
+        "Your output is inconsistent and I reject it with a request for you to try again.\n"
+            .to_string();
+    let masks = |rule: usize| {
+        rule.checked_sub(1)
+            .and_then(|index| report.masks_by_index.get(index))
+    };
+    for rule in matched.iter().filter(|r| !reported.contains(r)) {
+        for mask in masks(*rule).into_iter().flatten() {
+            content += &format!(
+                "- Rule {rule}: Either set \"{mask}\" to its default or append {rule} to \"__rule_numbers__\".\n"
+            );
+        }
+    }
+    for rule in reported.iter().filter(|r| !matched.contains(r)) {
+        match masks(*rule) {
+            Some(masks) => {
+                for mask in masks {
+                    content += &format!(
+                        "- Rule {rule}: Either set \"{mask}\" to a non-default value or remove {rule} from \"__rule_numbers__\".\n"
+                    );
+                }
+            }
+            None => content += &format!("- Rule number {rule} doesn't exist.\n"),
+        }
+    }
+    content
+}
+
+////////////////////////////////////////////// Backend /////////////////////////////////////////////
+
+string_enum! {
+    /// Which kind of server a backend talks to
+    pub enum Backend {
+        Anthropic => "anthropic",
+        OpenAi => "openai",
+        Ollama => "ollama",
+    }
+}
+
+/// Command-line flags for choosing a language model
+///
+/// Nest this in a binary's options as `llm` to get `--llm-backend`, `--llm-model`, and
+/// `--llm-url`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, arrrg_derive::CommandLine)]
+pub struct LlmOptions {
+    #[arrrg(optional, "Language model backend: anthropic, openai, or ollama")]
+    pub backend: Option<String>,
+    #[arrrg(optional, "Model to use")]
+    pub model: Option<String>,
+    #[arrrg(optional, "Base URL of the language model API")]
+    pub url: Option<String>,
+}
+
+impl LlmOptions {
+    /// Connect to the backend chosen by these flags, then the config file, then the defaults
+    ///
+    /// `binary` selects a per-binary model from the config file.  `default_model` is only used
+    /// with the Anthropic backend; other backends need a model to be chosen explicitly.
+    pub fn connect(
+        &self,
+        config: &LlmConfig,
+        binary: &str,
+        default_model: &str,
+    ) -> Result<Box<dyn Llm>, Error> {
+        let backend = self
+            .backend
+            .clone()
+            .map(Backend::from)
+            .or_else(|| config.backend.clone())
+            .unwrap_or(Backend::Anthropic);
+        let model = self
+            .model
+            .clone()
+            .or_else(|| config.models.get(binary).cloned())
+            .or_else(|| config.model.clone());
+        let url = self.url.clone().or_else(|| config.url.clone());
+        let needs_model = || {
+            Error::Config(format!(
+                "the {} backend needs a model; pass --llm-model or set llm.model",
+                backend
+            ))
+        };
+        match backend {
+            Backend::Anthropic => Ok(Box::new(AnthropicLlm::new(
+                model.unwrap_or_else(|| default_model.to_string()),
+                url,
+            )?)),
+            Backend::OpenAi => Ok(Box::new(
+                OpenAiLlm::new(
+                    url.unwrap_or_else(|| OPENAI_URL.to_string()),
+                    model.ok_or_else(needs_model)?,
+                )
+                .with_api_key(std::env::var("OPENAI_API_KEY").ok()),
+            )),
+            Backend::Ollama => Ok(Box::new(OpenAiLlm::new(
+                url.unwrap_or_else(|| OLLAMA_URL.to_string()),
+                model.ok_or_else(needs_model)?,
+            ))),
+            Backend::Unknown(name) => Err(Error::Config(format!(
+                "unknown language model backend {:?}; expected anthropic, openai, or ollama",
+                name
+            ))),
+        }
+    }
+}
+
+/////////////////////////////////////////// AnthropicLlm ///////////////////////////////////////////
+
+/// The Anthropic Messages API
+pub struct AnthropicLlm {
+    client: Anthropic,
+    model: String,
+}
+
+impl AnthropicLlm {
+    /// Connect with the key in `CLAUDIUS_API_KEY` or `ANTHROPIC_API_KEY`
+    pub fn new(model: impl Into<String>, url: Option<String>) -> Result<Self, Error> {
+        let mut client = Anthropic::new(None)?;
+        if let Some(url) = url {
+            client = client.with_base_url(format!("{}/", url.trim_end_matches('/')));
+        }
+        Ok(Self {
+            client,
+            model: model.into(),
+        })
+    }
+}
+
+impl Llm for AnthropicLlm {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn send(&self, mut req: MessageCreateParams) -> BoxFuture<'_, Result<Message, Error>> {
+        req.model = model(&self.model);
+        async move { Ok(self.client.send(req).await?) }.boxed()
+    }
+
+    fn stream(
+        &self,
+        mut req: MessageCreateParams,
+    ) -> BoxFuture<'_, Result<BoxStream<'_, Result<String, Error>>, Error>> {
+        req.model = model(&self.model);
+        async move {
+            let events = self.client.stream(req).await?;
+            let text = events.filter_map(|event| async move {
+                match event {
+                    Ok(MessageStreamEvent::ContentBlockDelta(delta)) => match delta.delta {
+                        ContentBlockDelta::TextDelta(t) => Some(Ok(t.text)),
+                        _ => None,
+                    },
+                    Ok(_) => None,
+                    Err(err) => Some(Err(Error::Anthropic(err))),
+                }
+            });
+            Ok(text.boxed())
+        }
+        .boxed()
+    }
+}
+
+fn model(name: &str) -> Model {
+    name.parse()
+        .unwrap_or_else(|_| Model::Custom(name.to_string()))
+}
+
+//////////////////////////////////////////// OpenAiLlm /////////////////////////////////////////////
+
+/// An OpenAI-compatible chat completions API, such as OpenAI itself, Ollama, or llama.cpp
+pub struct OpenAiLlm {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiLlm {
+    /// Talk to the API rooted at `url`, e.g. `http://localhost:11434/v1`
+    pub fn new(url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+            api_key: None,
+        }
+    }
+
+    /// Authenticate with a bearer token; local servers usually need none
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    async fn post(&self, body: serde_json::Value) -> Result<reqwest::Response, Error> {
+        let url = format!("{}/chat/completions", self.url);
+        let mut request = self.client.post(&url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await.map_err(|e| Error::network(&url, e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Llm(format!(
+                "HTTP {} from {}: {}",
+                status, url, body
+            )));
+        }
+        Ok(response)
+    }
+}
+
+impl Llm for OpenAiLlm {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn send(&self, req: MessageCreateParams) -> BoxFuture<'_, Result<Message, Error>> {
+        async move {
+            let response = self.post(chat_request(&self.model, &req, false)).await?;
+            let url = response.url().to_string();
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| Error::network(&url, e))?;
+            // Chat completions are not GitHub models, so nothing unknown is reported.
+            let (completion, _): (ChatCompletion, _) = decode(&url, &bytes)?;
+            completion.into_message(&self.model)
+        }
+        .boxed()
+    }
+
+    fn stream(
+        &self,
+        req: MessageCreateParams,
+    ) -> BoxFuture<'_, Result<BoxStream<'_, Result<String, Error>>, Error>> {
+        async move {
+            let response = self.post(chat_request(&self.model, &req, true)).await?;
+            let url = response.url().to_string();
+            let lines = server_sent_data(response.bytes_stream(), url);
+            let text = lines.filter_map(|data| async move {
+                match data {
+                    Ok(data) => {
+                        let chunk: ChatChunk = match serde_json::from_str(&data) {
+                            Ok(chunk) => chunk,
+                            Err(err) => return Some(Err(Error::Llm(err.to_string()))),
+                        };
+                        chunk
+                            .choices
+                            .into_iter()
+                            .next()
+                            .and_then(|c| c.delta.content)
+                            .filter(|t| !t.is_empty())
+                            .map(Ok)
+                    }
+                    Err(err) => Some(Err(err)),
+                }
+            });
+            Ok(text.boxed())
+        }
+        .boxed()
+    }
+}
+
+/// Translate an Anthropic-style request into a chat completions request
+fn chat_request(model: &str, req: &MessageCreateParams, stream: bool) -> serde_json::Value {
+    let mut messages = Vec::new();
+    if let Some(system) = &req.system {
+        let system = match system {
+            SystemPrompt::String(s) => s.clone(),
+            SystemPrompt::Blocks(blocks) => blocks
+                .iter()
+                .map(|b| b.block.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        messages.push(serde_json::json!({"role": "system", "content": system}));
+    }
+    for message in &req.messages {
+        let role = match message.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+        let blocks = match &message.content {
+            MessageParamContent::String(s) => {
+                messages.push(serde_json::json!({"role": role, "content": s}));
+                continue;
+            }
+            MessageParamContent::Array(blocks) => blocks,
+        };
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block {
+                ContentBlock::Text(t) => content.push_str(&t.text),
+                ContentBlock::ToolUse(t) => tool_calls.push(serde_json::json!({
+                    "id": t.id,
+                    "type": "function",
+                    "function": {"name": t.name, "arguments": t.input.to_string()},
+                })),
+                ContentBlock::ToolResult(r) => {
+                    let result = match &r.content {
+                        Some(ToolResultBlockContent::String(s)) => s.clone(),
+                        Some(ToolResultBlockContent::Array(parts)) => parts
+                            .iter()
+                            .filter_map(|p| match p {
+                                claudius::Content::Text(t) => Some(t.text.as_str()),
+                                _ => None,
+                            })
+                            .collect(),
+                        None => String::new(),
+                    };
+                    messages.push(serde_json::json!({
+                        "role": "tool",
+                        "tool_call_id": r.tool_use_id,
+                        "content": result,
+                    }));
+                }
+                _ => {}
+            }
+        }
+        if !content.is_empty() || !tool_calls.is_empty() {
+            let mut message = serde_json::json!({"role": role, "content": content});
+            if !tool_calls.is_empty() {
+                message["tool_calls"] = tool_calls.into();
+            }
+            messages.push(message);
+        }
+    }
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": req.max_tokens,
+        "stream": stream,
+    });
+    if let Some(temperature) = req.temperature {
+        body["temperature"] = temperature.into();
+    }
+    if let Some(stop) = &req.stop_sequences {
+        body["stop"] = stop.clone().into();
+    }
+    if let Some(tools) = &req.tools {
+        let tools = tools
+            .iter()
+            .filter_map(|tool| match tool {
+                ToolUnionParam::CustomTool(t) => Some(serde_json::json!({
+                    "type": "function",
+                    "function": {
+                        "name": t.name,
+                        "description": t.description.clone().unwrap_or_default(),
+                        "parameters": t.input_schema,
+                    },
+                })),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        body["tools"] = tools.into();
+    }
+    if let Some(choice) = &req.tool_choice {
+        body["tool_choice"] = match choice {
+            ToolChoice::Auto { .. } => "auto".into(),
+            ToolChoice::Any { .. } => "required".into(),
+            ToolChoice::None => "none".into(),
+            ToolChoice::Tool { name, .. } => {
+                serde_json::json!({"type": "function", "function": {"name": name}})
+            }
+        };
+    }
+    body
+}
+
+// Responses from third-party servers carry extensions, so unknown fields are ignored here.
+
+#[derive(serde::Deserialize)]
+struct ChatCompletion {
+    id: Option<String>,
+    choices: Vec<ChatChoice>,
+    usage: Option<ChatUsage>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+    tool_calls: Option<Vec<ChatToolCall>>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatToolCall {
+    id: Option<String>,
+    function: ChatFunctionCall,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatFunctionCall {
+    name: String,
+    /// A JSON-encoded string per the OpenAI API, though some servers send an object
+    arguments: serde_json::Value,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatUsage {
+    prompt_tokens: i32,
+    completion_tokens: i32,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatChunk {
+    choices: Vec<ChatChunkChoice>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatChunkChoice {
+    delta: ChatDelta,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatDelta {
+    content: Option<String>,
+}
+
+impl ChatCompletion {
+    fn into_message(self, model: &str) -> Result<Message, Error> {
+        let choice = self
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Llm(format!("{} returned no choices", model)))?;
+        let mut content = Vec::new();
+        if let Some(text) = choice.message.content.filter(|t| !t.is_empty()) {
+            content.push(ContentBlock::Text(TextBlock::new(text)));
+        }
+        for (index, call) in choice.message.tool_calls.into_iter().flatten().enumerate() {
+            let input = match call.function.arguments {
+                serde_json::Value::String(arguments) => {
+                    serde_json::from_str(&arguments).map_err(|e| {
+                        Error::Llm(format!(
+                            "{} called {} with arguments that are not JSON: {}",
+                            model, call.function.name, e
+                        ))
+                    })?
+                }
+                arguments => arguments,
+            };
+            let id = call.id.unwrap_or_else(|| format!("call_{}", index));
+            content.push(ContentBlock::ToolUse(ToolUseBlock::new(
+                id,
+                call.function.name,
+                input,
+            )));
+        }
+        let usage = self
+            .usage
+            .map(|u| claudius::Usage::new(u.prompt_tokens, u.completion_tokens))
+            .unwrap_or_else(|| claudius::Usage::new(0, 0));
+        let mut message = Message::new(
+            self.id.unwrap_or_default(),
+            content,
+            Model::Custom(model.to_string()),
+            usage,
+        );
+        message.stop_reason = match choice.finish_reason.as_deref() {
+            Some("tool_calls") => Some(StopReason::ToolUse),
+            Some("length") => Some(StopReason::MaxTokens),
+            Some(_) => Some(StopReason::EndTurn),
+            None => None,
+        };
+        Ok(message)
+    }
+}
+
+/// The `data:` payloads of a server-sent event stream, ending at `[DONE]`
+fn server_sent_data<'a, B: AsRef<[u8]> + 'a>(
+    bytes: impl futures::Stream<Item = reqwest::Result<B>> + Send + 'a,
+    url: String,
+) -> BoxStream<'a, Result<String, Error>> {
+    let state = (bytes.boxed(), Vec::<u8>::new(), url, false);
+    futures::stream::unfold(state, |(mut bytes, mut buffer, url, done)| async move {
+        if done {
+            return None;
+        }
+        loop {
+            if let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=newline).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim().to_string();
+                if data == "[DONE]" {
+                    return None;
+                }
+                return Some((Ok(data), (bytes, buffer, url, false)));
+            }
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(chunk.as_ref()),
+                Some(Err(err)) => {
+                    let err = Error::network(&url, err);
+                    return Some((Err(err), (bytes, buffer, url, true)));
+                }
+                None => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+//////////////////////////////////////////// ScriptedLlm ///////////////////////////////////////////
+
+/// A deterministic backend for tests that replays queued responses in order
+///
+/// Every request is recorded, and a request beyond the end of the script is an error.
+#[derive(Default)]
+pub struct ScriptedLlm {
+    responses: Mutex<VecDeque<Message>>,
+    requests: Mutex<Vec<MessageCreateParams>>,
+}
+
+impl ScriptedLlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response made of `text`
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.push(
+            vec![ContentBlock::Text(TextBlock::new(text))],
+            StopReason::EndTurn,
+        )
+    }
+
+    /// Queue a structured response, as from [`structured`] or [`apply_policies`]
+    pub fn json(self, value: serde_json::Value) -> Self {
+        let id = format!("toolu_{}", self.responses.lock().unwrap().len());
+        self.push(
+            vec![ContentBlock::ToolUse(ToolUseBlock::new(
+                id,
+                OUTPUT_TOOL,
+                value,
+            ))],
+            StopReason::ToolUse,
+        )
+    }
+
+    fn push(self, content: Vec<ContentBlock>, stop_reason: StopReason) -> Self {
+        let message = canned("scripted", content, stop_reason);
+        self.responses.lock().unwrap().push_back(message);
+        self
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<MessageCreateParams> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The number of responses not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    fn next(&self, req: MessageCreateParams) -> Result<Message, Error> {
+        self.requests.lock().unwrap().push(req);
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| Error::Llm("scripted model has no responses left".to_string()))
+    }
+}
+
+impl Llm for ScriptedLlm {
+    fn model(&self) -> &str {
+        "scripted"
+    }
+
+    fn send(&self, req: MessageCreateParams) -> BoxFuture<'_, Result<Message, Error>> {
+        let resp = self.next(req);
+        async move { resp }.boxed()
+    }
+
+    fn stream(
+        &self,
+        req: MessageCreateParams,
+    ) -> BoxFuture<'_, Result<BoxStream<'_, Result<String, Error>>, Error>> {
+        let resp = self.next(req);
+        async move {
+            let words = text(&resp?)
+                .split_inclusive(' ')
+                .map(|w| Ok(w.to_string()))
+                .collect::<Vec<_>>();
+            Ok(futures::stream::iter(words).boxed())
+        }
+        .boxed()
+    }
+}
+
+//////////////////////////////////////////// KeywordLlm ////////////////////////////////////////////
+
+/// A stand-in model that applies policies by keyword instead of by judgement
+///
+/// A rule matches when the text contains, ignoring case, every phrase its criteria put in double
+/// quotes; a rule that quotes nothing never matches.  policyai's masks are deterministic, so the
+/// fake answers in exactly the form [`apply_policies`] checks.  That makes it useful for
+/// exercising policy files, and the tools built around them, without a model.
+pub struct KeywordLlm {
+    rules: Vec<KeywordRule>,
+}
+
+struct KeywordRule {
+    phrases: Vec<String>,
+    /// The masked output that reports this rule as matched
+    output: serde_json::Map<String, serde_json::Value>,
+}
+
+impl KeywordLlm {
+    /// A fake for `policies`, which must be added to the [`Manager`] in the same order
+    pub fn new(policies: &[Policy]) -> Result<Self, Error> {
+        let mut builder = ReportBuilder::default();
+        for policy in policies {
+            builder.add_policy(policy).map_err(ApplyError::from)?;
+        }
+        let masks = builder.consume_ir(serde_json::json!({}))?.masks_by_index;
+        let rules = policies
+            .iter()
+            .zip(masks)
+            .map(|(policy, masks)| {
+                let phrases = policy
+                    .prompt
+                    .split('"')
+                    .skip(1)
+                    .step_by(2)
+                    .map(str::to_lowercase)
+                    .collect();
+                // Masks are handed out to the fields the action sets, in declaration order.
+                let fields = policy
+                    .r#type
+                    .fields
+                    .iter()
+                    .filter(|field| policy.action.get(field.name()).is_some());
+                let output = fields
+                    .zip(masks)
+                    .map(|(field, mask)| {
+                        let value = match field {
+                            Field::StringEnum { .. } => serde_json::Value::Bool(true),
+                            _ => policy.action[field.name()].clone(),
+                        };
+                        (mask, value)
+                    })
+                    .collect();
+                KeywordRule { phrases, output }
+            })
+            .collect();
+        Ok(Self { rules })
+    }
+
+    fn decide(&self, req: &MessageCreateParams) -> Result<Message, Error> {
+        if !req
+            .tool_choice
+            .as_ref()
+            .is_some_and(|choice| *choice == ToolChoice::tool(OUTPUT_TOOL))
+        {
+            return Err(Error::Llm(
+                "the keyword model only answers policy requests".to_string(),
+            ));
+        }
+        let prompt = req
+            .messages
+            .iter()
+            .flat_map(|message| match &message.content {
+                MessageParamContent::String(text) => vec![text.clone()],
+                MessageParamContent::Array(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(text) => Some(text.text.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = prompt
+            .rsplit_once("<text>")
+            .and_then(|(_, rest)| rest.split_once("</text>"))
+            .map(|(text, _)| text.to_lowercase())
+            .unwrap_or_default();
+        let mut output = serde_json::Map::new();
+        let mut matched = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.phrases.is_empty() && rule.phrases.iter().all(|p| text.contains(p.as_str())) {
+                matched.push(index + 1);
+                output.extend(rule.output.clone());
+            }
+        }
+        output.insert("__rule_numbers__".to_string(), matched.into());
+        Ok(canned(
+            "keyword",
+            vec![ContentBlock::ToolUse(ToolUseBlock::new(
+                "toolu_keyword",
+                OUTPUT_TOOL,
+                serde_json::Value::Object(output),
+            ))],
+            StopReason::ToolUse,
+        ))
+    }
+}
+
+impl Llm for KeywordLlm {
+    fn model(&self) -> &str {
+        "keyword"
+    }
+
+    fn send(&self, req: MessageCreateParams) -> BoxFuture<'_, Result<Message, Error>> {
+        let resp = self.decide(&req);
+        async move { resp }.boxed()
+    }
+
+    fn stream(
+        &self,
+        _: MessageCreateParams,
+    ) -> BoxFuture<'_, Result<BoxStream<'_, Result<String, Error>>, Error>> {
+        async move {
+            Err(Error::Llm(
+                "the keyword model only answers policy requests".to_string(),
+            ))
+        }
+        .boxed()
+    }
+}
+
+/// A response as if from `model`, for the stand-in backends
+fn canned(model: &str, content: Vec<ContentBlock>, stop_reason: StopReason) -> Message {
+    let mut message = Message::new(
+        model.to_string(),
+        content,
+        Model::Custom(model.to_string()),
+        claudius::Usage::new(10, 1),
+    );
+    message.stop_reason = Some(stop_reason);
+    message
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{Decision, DecisionAction, Priority};
+
+    fn request(prompt: &str) -> MessageCreateParams {
+        MessageCreateParams {
+            max_tokens: 64,
+            messages: vec![prompt.into()],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn scripted_replays_in_order() {
+        let llm = ScriptedLlm::new().text("first").text("second");
+        assert_eq!(complete(&llm, request("a")).await.unwrap(), "first");
+        assert_eq!(complete(&llm, request("b")).await.unwrap(), "second");
+        assert!(matches!(
+            complete(&llm, request("c")).await,
+            Err(Error::Llm(_))
+        ));
+        assert_eq!(llm.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn scripted_streams_text() {
+        let llm = ScriptedLlm::new().text("one two three");
+        let stream = llm.stream(request("a")).await.unwrap();
+        let chunks = stream.map(Result::unwrap).collect::<Vec<_>>().await;
+        assert_eq!(chunks, vec!["one ", "two ", "three"]);
+    }
+
+    #[tokio::test]
+    async fn structured_forces_tool() {
+        let llm = ScriptedLlm::new().json(serde_json::json!({"title": "t"}));
+        let schema = serde_json::json!({"type": "object"});
+        let value = structured(&llm, request("a"), schema).await.unwrap();
+        assert_eq!(value, serde_json::json!({"title": "t"}));
+        let req = &llm.requests()[0];
+        assert_eq!(req.tool_choice, Some(ToolChoice::tool(OUTPUT_TOOL)));
+    }
+
+    #[tokio::test]
+    async fn keyword_model_matches_quoted_phrases() {
+        let policies = crate::policy::parse_policies(
+            "If it mentions \"dependabot\", output {\"action\": \"mark-done\", \"label\": [\"deps\"]}\n\
+             If it mentions \"security\", output {\"action\": \"mark-unread\", \"priority\": \"high\", \"star_repo\": true}\n\
+             If it seems urgent, output {\"priority\": \"high\"}\n",
+        )
+        .unwrap()
+        .policies;
+        let decide = |text: &'static str| {
+            let policies = policies.clone();
+            async move {
+                let llm = KeywordLlm::new(&policies).unwrap();
+                let mut manager = Manager::default();
+                for policy in policies {
+                    manager.add(policy);
+                }
+                let report = apply_policies(&llm, &mut manager, request(""), text, None)
+                    .await
+                    .unwrap();
+                Decision::from_value(report.value()).unwrap()
+            }
+        };
+
+        let decision = decide("<title>Bump serde (Dependabot)</title>").await;
+        assert_eq!(Some(DecisionAction::MarkDone), decision.action);
+        assert_eq!(vec!["deps".to_string()], decision.label);
+        let decision = decide("dependabot security fix, urgent").await;
+        assert_eq!(Some(DecisionAction::MarkUnread), decision.action);
+        assert_eq!(Some(Priority::High), decision.priority);
+        assert!(decision.star_repo);
+        let decision = decide("nothing to see").await;
+        assert_eq!(None, decision.action);
+        assert_eq!(None, decision.priority);
+    }
+
+    #[tokio::test]
+    async fn apply_policies_without_policies() {
+        let llm = ScriptedLlm::new().json(serde_json::json!({"__rule_numbers__": []}));
+        let mut manager = Manager::default();
+        let mut usage = Usage::default();
+        let report = apply_policies(
+            &llm,
+            &mut manager,
+            request(""),
+            "<notification/>",
+            Some(&mut usage),
+        )
+        .await
+        .unwrap();
+        assert!(report.rules_matched.is_empty());
+        assert_eq!(usage.iterations, 1);
+        assert_eq!(usage.claudius_usage.unwrap().input_tokens, 10);
+    }
+
+    #[tokio::test]
+    async fn apply_policies_retries_missing_rule_numbers() {
+        let llm = ScriptedLlm::new()
+            .json(serde_json::json!({}))
+            .json(serde_json::json!({"__rule_numbers__": []}));
+        let mut manager = Manager::default();
+        apply_policies(&llm, &mut manager, request(""), "text", None)
+            .await
+            .unwrap();
+        let retry = &llm.requests()[1];
+        let last = retry.messages.last().unwrap();
+        assert_eq!(last.role, MessageRole::User);
+        let MessageParamContent::Array(blocks) = &last.content else {
+            panic!("expected a tool result");
+        };
+        assert!(matches!(&blocks[0], ContentBlock::ToolResult(r) if r.is_error == Some(true)));
+    }
+
+    #[test]
+    fn chat_request_translates_tools() {
+        let mut req = request("hello");
+        req.system = Some(SystemPrompt::String("be brief".to_string()));
+        force_output_tool(&mut req, serde_json::json!({"type": "object"}));
+        let body = chat_request("llama3.2", &req, false);
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "hello");
+        assert_eq!(body["tools"][0]["function"]["name"], OUTPUT_TOOL);
+        assert_eq!(body["tool_choice"]["function"]["name"], OUTPUT_TOOL);
+    }
+
+    #[test]
+    fn chat_request_translates_tool_results() {
+        let mut req = request("hello");
+        req.messages.push(MessageParam::new_with_blocks(
+            vec![ContentBlock::ToolUse(ToolUseBlock::new(
+                "call_0",
+                OUTPUT_TOOL,
+                serde_json::json!({"a": 1}),
+            ))],
+            MessageRole::Assistant,
+        ));
+        let mut result = ToolResultBlock::new("call_0".to_string());
+        result.content = Some(ToolResultBlockContent::String("again".to_string()));
+        req.messages.push(MessageParam::new_with_blocks(
+            vec![ContentBlock::ToolResult(result)],
+            MessageRole::User,
+        ));
+        let body = chat_request("m", &req, false);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            r#"{"a":1}"#
+        );
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_0");
+        assert_eq!(messages[2]["content"], "again");
+    }
+
+    #[test]
+    fn completion_becomes_message() {
+        let completion: ChatCompletion = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": {"name": "output_json", "arguments": "{\"x\": true}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+        }))
+        .unwrap();
+        let message = completion.into_message("llama3.2").unwrap();
+        let tool = tool_use(&message).unwrap();
+        assert_eq!(tool.id, "call_abc");
+        assert_eq!(tool.input, serde_json::json!({"x": true}));
+        assert_eq!(message.usage.input_tokens, 12);
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+    }
+
+    #[test]
+    fn connect_requires_model_for_local_backends() {
+        let opts = LlmOptions {
+            backend: Some("ollama".to_string()),
+            ..Default::default()
+        };
+        let config = LlmConfig::default();
+        assert!(matches!(
+            opts.connect(&config, "ghai-rewrite-issue", "claude-haiku-4-5"),
+            Err(Error::Config(_))
+        ));
+        let config = LlmConfig {
+            models: [("ghai-rewrite-issue".to_string(), "llama3.2".to_string())].into(),
+            ..Default::default()
+        };
+        let llm = opts
+            .connect(&config, "ghai-rewrite-issue", "claude-haiku-4-5")
+            .unwrap();
+        assert_eq!(llm.model(), "llama3.2");
+    }
+
+    #[test]
+    fn connect_rejects_unknown_backend() {
+        let opts = LlmOptions {
+            backend: Some("palm".to_string()),
+            model: Some("m".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            opts.connect(&LlmConfig::default(), "x", "y"),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -1,11 +1,22 @@
-use policyai::PolicyType;
+use policyai::{Policy, PolicyType};
+
+use crate::error::Error;
+use crate::parser::{parse_lines_with_line_numbers, ParseErrorWithContext};
+use crate::rules::Rule;
 
 ////////////////////////////////////////////// policy //////////////////////////////////////////////
 
+/// The policy type every notification policy is written against
+///
+/// Actions are listed from most to least drastic so that, when policies disagree, the most
+/// conservative one wins.  `star_repo` and `apply_label` combine with any action; the latter adds
+/// the decision's `label`s to the underlying issue or pull request.
 pub const POLICY: &str = r#"type ghai::Policy {
-    action: ["mark-read", "mark-unread"] @ highest wins,
+    action: ["ignore", "unsubscribe", "mark-done", "mark-read", "mark-unread"] @ highest wins,
     priority: ["low", "medium", "high"] @ highest wins,
     label: [string],
+    star_repo: bool @ sticky,
+    apply_label: bool @ sticky,
 }
 "#;
 
@@ -13,16 +24,123 @@ pub fn get_policy_type() -> PolicyType {
     PolicyType::parse(POLICY).unwrap()
 }
 
+/// The contents of policy files: rules decided mechanically and policies left to the model
+#[derive(Clone, Debug, Default)]
+pub struct PolicySet {
+    pub rules: Vec<Rule>,
+    pub policies: Vec<Policy>,
+}
+
+/// Turn the contents of policy files into rules and policies, checking that each action is a
+/// [`Decision`]
+///
+/// Each non-empty line is a criterion followed by the JSON to output when it holds.  Lines whose
+/// criterion starts with `when` are [`Rule`]s; the rest are policies for the model.
+pub fn parse_policies(content: &str) -> Result<PolicySet, Error> {
+    let mut set = PolicySet::default();
+    for line in parse_lines_with_line_numbers(content) {
+        match line {
+            Ok((_, prompt, action)) if Rule::is_rule(&prompt) => {
+                set.rules.push(Rule::parse(&prompt, action)?);
+            }
+            Ok((_, prompt, action)) => {
+                Decision::from_value(action.clone())?;
+                set.policies.push(Policy {
+                    r#type: get_policy_type(),
+                    prompt,
+                    action,
+                });
+            }
+            Err(ParseErrorWithContext::EmptyLine { .. }) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(set)
+}
+
 ///////////////////////////////////////////// Decision /////////////////////////////////////////////
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+string_enum! {
+    /// What a policy decided to do with a notification
+    pub enum DecisionAction {
+        /// Mute the thread for good, then mark it read
+        Ignore => "ignore",
+        /// Stop notifications until the user participates again, then mark it read
+        Unsubscribe => "unsubscribe",
+        /// Remove the thread from the inbox
+        MarkDone => "mark-done",
+        MarkRead => "mark-read",
+        MarkUnread => "mark-unread",
+    }
+}
+
+string_enum! {
+    /// How urgently a notification kept unread needs attention
+    pub enum Priority {
+        Low => "low",
+        Medium => "medium",
+        High => "high",
+    }
+}
+
+impl Priority {
+    /// Where this priority sorts: higher is more urgent, and unknown values sort below `low`
+    pub fn rank(&self) -> u8 {
+        match self {
+            Priority::High => 3,
+            Priority::Medium => 2,
+            Priority::Low => 1,
+            Priority::Unknown(_) => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Decision {
+    /// `None` when no policy chose an action
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub action: Option<DecisionAction>,
+    /// `None` when no policy assigned a priority
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub priority: Option<Priority>,
     #[serde(default)]
-    pub action: String,
+    pub label: Vec<String>,
+    /// Star the notification's repository
     #[serde(default)]
-    pub priority: String,
+    pub star_repo: bool,
+    /// Add `label` to the underlying issue or pull request
     #[serde(default)]
-    pub label: Vec<String>,
+    pub apply_label: bool,
+}
+
+fn empty_as_none<'de, D: serde::Deserializer<'de>, T: From<String>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    let value: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(value.filter(|v| !v.is_empty()).map(T::from))
+}
+
+impl Decision {
+    /// Decode a decision from a policy's action or a policy report
+    pub fn from_value(value: serde_json::Value) -> Result<Self, Error> {
+        serde_path_to_error::deserialize(value).map_err(|err| Error::InvalidDecision {
+            path: err.path().to_string(),
+            source: err.into_inner(),
+        })
+    }
+}
+
+/////////////////////////////////////////// Explanation ////////////////////////////////////////////
+
+/// Why a decision was made
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Explanation {
+    /// The prompts of the policies that matched, or the rule that decided
+    #[serde(default)]
+    pub policies: Vec<String>,
+    /// The model's own account of its decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
 }
 
 /////////////////////////////////////////////// tests //////////////////////////////////////////////
@@ -41,4 +159,87 @@ mod tests {
     fn get_policy_type_returns_valid_policy() {
         let _policy_type = get_policy_type();
     }
+
+    #[test]
+    fn parse_policies_checks_actions() {
+        let set = parse_policies(
+            "If it is a release, output {\"action\": \"mark-read\"}\n\n\
+             when reason == \"ci_activity\" output {\"action\": \"mark-done\"}\n\
+             If it mentions me, output {\"action\": \"mark-unread\", \"priority\": \"high\"}\n",
+        )
+        .unwrap();
+        assert_eq!(2, set.policies.len());
+        assert_eq!("If it is a release, output", set.policies[0].prompt);
+        assert_eq!(1, set.rules.len());
+        assert_eq!(Some(DecisionAction::MarkDone), set.rules[0].decision.action);
+        assert!(matches!(
+            parse_policies("when colour == \"red\" output {}"),
+            Err(Error::InvalidRule { .. })
+        ));
+        assert!(matches!(
+            parse_policies("no json here"),
+            Err(Error::ParseWithContext(
+                ParseErrorWithContext::NoValidJson { line_number: 1, .. }
+            ))
+        ));
+        assert!(matches!(
+            parse_policies("Labels, output {\"label\": \"one\"}"),
+            Err(Error::InvalidDecision { .. })
+        ));
+    }
+
+    #[test]
+    fn decision_from_value() {
+        let decision =
+            Decision::from_value(serde_json::json!({"action": "mark-read", "label": ["ci"]}))
+                .unwrap();
+        assert_eq!(decision.action, Some(DecisionAction::MarkRead));
+        assert_eq!(decision.priority, None);
+        assert_eq!(decision.label, vec!["ci".to_string()]);
+    }
+
+    #[test]
+    fn decision_without_action() {
+        let decision = Decision::from_value(serde_json::json!({"action": ""})).unwrap();
+        assert_eq!(decision.action, None);
+        let decision = Decision::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(decision.action, None);
+    }
+
+    #[test]
+    fn decision_priority() {
+        let decision = Decision::from_value(serde_json::json!({"priority": "high"})).unwrap();
+        assert_eq!(decision.priority, Some(Priority::High));
+        let decision = Decision::from_value(serde_json::json!({"priority": ""})).unwrap();
+        assert_eq!(decision.priority, None);
+        assert!(Priority::High.rank() > Priority::Medium.rank());
+        assert!(Priority::Low.rank() > Priority::from("urgent").rank());
+    }
+
+    #[test]
+    fn decision_with_side_effects() {
+        let decision = Decision::from_value(serde_json::json!({
+            "action": "unsubscribe",
+            "label": ["noise"],
+            "star_repo": true,
+            "apply_label": true,
+        }))
+        .unwrap();
+        assert_eq!(decision.action, Some(DecisionAction::Unsubscribe));
+        assert!(decision.star_repo);
+        assert!(decision.apply_label);
+        let decision = Decision::from_value(serde_json::json!({"action": "mark-done"})).unwrap();
+        assert_eq!(decision.action, Some(DecisionAction::MarkDone));
+        assert!(!decision.star_repo);
+        assert!(!decision.apply_label);
+    }
+
+    #[test]
+    fn decision_from_value_reports_path() {
+        let err = Decision::from_value(serde_json::json!({"label": ["ci", 7]})).unwrap_err();
+        match err {
+            Error::InvalidDecision { path, .. } => assert_eq!(path, "label[1]"),
+            _ => panic!("expected an invalid decision, got {:?}", err),
+        }
+    }
 }
@@ -0,0 +1,314 @@
+//! The review queue: the notifications left unread, ordered by what needs attention first.
+//!
+//! Entries are sorted by the priority their policies assigned, most urgent first, and then by age,
+//! oldest first.  They are grouped by policy-assigned label; an entry with several labels appears
+//! under each, and entries without any are gathered in a final group.
+
+use chrono::{DateTime, Utc};
+
+use crate::policy::{Decision, Priority};
+use crate::types::{Notification, SubjectType};
+
+///////////////////////////////////////////// QueueFormat //////////////////////////////////////////
+
+string_enum! {
+    /// How to render the review queue
+    pub enum QueueFormat {
+        Text => "text",
+        Markdown => "markdown",
+        Json => "json",
+    }
+}
+
+////////////////////////////////////////////// QueueEntry //////////////////////////////////////////
+
+/// One notification waiting for review
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
+pub struct QueueEntry {
+    pub thread_id: String,
+    pub priority: Option<Priority>,
+    pub labels: Vec<String>,
+    pub repository: String,
+    pub r#type: SubjectType,
+    pub title: String,
+    pub updated_at: DateTime<Utc>,
+    /// The one-line summary produced while deciding
+    pub summary: String,
+    pub html_url: String,
+}
+
+impl QueueEntry {
+    pub fn new(thread: &Notification, decision: &Decision, summary: &str) -> Self {
+        Self {
+            thread_id: thread.id.clone(),
+            priority: decision.priority.clone(),
+            labels: decision.label.clone(),
+            repository: thread.repository.full_name.clone(),
+            r#type: thread.subject.r#type.clone(),
+            title: thread.subject.title.clone(),
+            updated_at: thread.updated_at,
+            summary: summary.to_string(),
+            html_url: thread.html_url(),
+        }
+    }
+
+    /// Sorts most urgent first, then oldest first
+    fn sort_key(&self) -> (std::cmp::Reverse<u8>, DateTime<Utc>) {
+        let rank = self.priority.as_ref().map(Priority::rank).unwrap_or(0);
+        (std::cmp::Reverse(rank), self.updated_at)
+    }
+}
+
+////////////////////////////////////////////// QueueGroup //////////////////////////////////////////
+
+/// The entries filed under one label, most urgent first
+#[derive(Debug, serde::Serialize)]
+pub struct QueueGroup<'a> {
+    /// `None` for the entries without a label
+    pub label: Option<&'a str>,
+    pub notifications: Vec<&'a QueueEntry>,
+}
+
+////////////////////////////////////////////// ReviewQueue /////////////////////////////////////////
+
+#[derive(Debug, Default)]
+pub struct ReviewQueue {
+    entries: Vec<QueueEntry>,
+}
+
+impl ReviewQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry, replacing any earlier one for the same thread
+    pub fn push(&mut self, entry: QueueEntry) {
+        self.remove(&entry.thread_id);
+        self.entries.push(entry);
+    }
+
+    /// Drop the entry for a thread that no longer needs review
+    pub fn remove(&mut self, thread_id: &str) {
+        self.entries.retain(|e| e.thread_id != thread_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries grouped by label, with groups ordered by their most urgent entry
+    pub fn groups(&self) -> Vec<QueueGroup<'_>> {
+        let mut sorted = self.entries.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|e| e.sort_key());
+        let mut groups: Vec<QueueGroup<'_>> = Vec::new();
+        let mut unlabeled = Vec::new();
+        for entry in sorted {
+            if entry.labels.is_empty() {
+                unlabeled.push(entry);
+            }
+            for label in &entry.labels {
+                match groups.iter_mut().find(|g| g.label == Some(label.as_str())) {
+                    Some(group) => group.notifications.push(entry),
+                    None => groups.push(QueueGroup {
+                        label: Some(label),
+                        notifications: vec![entry],
+                    }),
+                }
+            }
+        }
+        if !unlabeled.is_empty() {
+            groups.push(QueueGroup {
+                label: None,
+                notifications: unlabeled,
+            });
+        }
+        groups
+    }
+
+    pub fn render(&self, format: &QueueFormat) -> String {
+        match format {
+            QueueFormat::Markdown => self.to_markdown(),
+            QueueFormat::Json => self.to_json(),
+            QueueFormat::Text | QueueFormat::Unknown(_) => self.to_text(),
+        }
+    }
+
+    /// A plain-text queue for the terminal
+    pub fn to_text(&self) -> String {
+        let mut out = format!("📥 Review queue ({})\n", self.len());
+        for group in self.groups() {
+            out += &format!("\n{}\n", group.label.unwrap_or("(unlabeled)"));
+            for entry in group.notifications {
+                out += &format!(
+                    "  [{}] {} ({}, updated {})\n",
+                    entry.priority.as_ref().map(Priority::as_str).unwrap_or("-"),
+                    entry.title,
+                    entry.repository,
+                    entry.updated_at.format("%Y-%m-%d %H:%M"),
+                );
+                out += &format!("      {}\n      {}\n", entry.summary, entry.html_url);
+            }
+        }
+        out
+    }
+
+    /// A Markdown queue, suitable for pasting into notes
+    pub fn to_markdown(&self) -> String {
+        let mut out = "# Review queue\n".to_string();
+        for group in self.groups() {
+            out += &format!("\n## {}\n\n", group.label.unwrap_or("Unlabeled"));
+            for entry in group.notifications {
+                let priority = entry
+                    .priority
+                    .as_ref()
+                    .map(|p| format!("**{}** ", p))
+                    .unwrap_or_default();
+                out += &format!(
+                    "- {}[{}]({}) ({}, updated {})\n  {}\n",
+                    priority,
+                    entry.title.replace('[', "\\[").replace(']', "\\]"),
+                    entry.html_url,
+                    entry.repository,
+                    entry.updated_at.format("%Y-%m-%d"),
+                    entry.summary,
+                );
+            }
+        }
+        out
+    }
+
+    /// The groups as a JSON array
+    pub fn to_json(&self) -> String {
+        let mut out = serde_json::to_string_pretty(&self.groups()).expect("groups serialize");
+        out.push('\n');
+        out
+    }
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        id: &str,
+        priority: Option<Priority>,
+        labels: &[&str],
+        updated_at: &str,
+    ) -> QueueEntry {
+        QueueEntry {
+            thread_id: id.to_string(),
+            priority,
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            repository: "rescrv/ghai".to_string(),
+            r#type: SubjectType::Issue,
+            title: format!("Issue {}", id),
+            updated_at: updated_at.parse().unwrap(),
+            summary: format!("Summary of {}", id),
+            html_url: format!("https://github.com/rescrv/ghai/issues/{}", id),
+        }
+    }
+
+    fn queue() -> ReviewQueue {
+        let mut queue = ReviewQueue::new();
+        queue.push(entry(
+            "1",
+            Some(Priority::Low),
+            &["ci"],
+            "2024-05-01T00:00:00Z",
+        ));
+        queue.push(entry("2", None, &[], "2024-04-01T00:00:00Z"));
+        queue.push(entry(
+            "3",
+            Some(Priority::High),
+            &["review"],
+            "2024-05-03T00:00:00Z",
+        ));
+        queue.push(entry(
+            "4",
+            Some(Priority::High),
+            &["ci", "review"],
+            "2024-05-02T00:00:00Z",
+        ));
+        queue
+    }
+
+    fn ids<'a>(group: &QueueGroup<'a>) -> Vec<&'a str> {
+        group
+            .notifications
+            .iter()
+            .map(|e| e.thread_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn groups_sort_by_priority_then_age() {
+        let queue = queue();
+        let groups = queue.groups();
+        let labels = groups.iter().map(|g| g.label).collect::<Vec<_>>();
+        assert_eq!(vec![Some("ci"), Some("review"), None], labels);
+        assert_eq!(vec!["4", "1"], ids(&groups[0]));
+        assert_eq!(vec!["4", "3"], ids(&groups[1]));
+        assert_eq!(vec!["2"], ids(&groups[2]));
+    }
+
+    #[test]
+    fn push_replaces_thread() {
+        let mut queue = queue();
+        queue.push(entry(
+            "1",
+            Some(Priority::Medium),
+            &[],
+            "2024-05-04T00:00:00Z",
+        ));
+        assert_eq!(4, queue.len());
+        queue.remove("2");
+        let groups = queue.groups();
+        assert_eq!(vec!["1"], ids(groups.last().unwrap()));
+    }
+
+    #[test]
+    fn render_markdown() {
+        let mut queue = ReviewQueue::new();
+        queue.push(entry(
+            "7",
+            Some(Priority::High),
+            &["ci"],
+            "2024-05-01T12:00:00Z",
+        ));
+        queue.push(entry("8", None, &[], "2024-05-01T10:00:00Z"));
+        assert_eq!(
+            "# Review queue\n\
+             \n## ci\n\n\
+             - **high** [Issue 7](https://github.com/rescrv/ghai/issues/7) (rescrv/ghai, updated 2024-05-01)\n  Summary of 7\n\
+             \n## Unlabeled\n\n\
+             - [Issue 8](https://github.com/rescrv/ghai/issues/8) (rescrv/ghai, updated 2024-05-01)\n  Summary of 8\n",
+            queue.render(&QueueFormat::Markdown)
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        let mut queue = ReviewQueue::new();
+        queue.push(entry(
+            "7",
+            Some(Priority::High),
+            &["ci"],
+            "2024-05-01T12:00:00Z",
+        ));
+        let value: serde_json::Value =
+            serde_json::from_str(&queue.render(&QueueFormat::Json)).unwrap();
+        assert_eq!("ci", value[0]["label"]);
+        assert_eq!("high", value[0]["notifications"][0]["priority"]);
+        assert_eq!("Issue", value[0]["notifications"][0]["type"]);
+        assert_eq!(
+            "https://github.com/rescrv/ghai/issues/7",
+            value[0]["notifications"][0]["html_url"]
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! Deterministic rules that decide notifications without a model.
+//!
+//! A policy line that starts with `when` is a rule rather than a policy for the model:
+//!
+//! ```text
+//! when reason == "ci_activity" output {"action": "mark-done"}
+//! when type == "PullRequest" and author ~ "*[bot]" and not repo ~ "rescrv/*" output {"action": "mark-read"}
+//! when merged or state == "closed" output {"action": "mark-read"}
+//! ```
+//!
+//! Every notification has `reason`, `type`, `repo`, `owner`, `title`, and `unread`.  Issues, pull
+//! requests, and discussions add `author`, `state`, and `labels`; commits, releases, and check
+//! suites add `author` (for a check suite, the actor who triggered the run), and check suites add
+//! `state` (the run's conclusion, or its status while it runs).  Pull requests and releases add
+//! `draft`, and pull requests add `merged`.
+//! Strings are compared with `==` and `!=`, or matched against a glob (`*` and `?`) with `~` and
+//! `!~`; against `labels`, a comparison holds if any label satisfies it.  Boolean fields
+//! (`unread`, `draft`, `merged`) stand alone as tests.  Tests combine with `and`, `or`, `not`, and
+//! parentheses.  A field the notification's subject does not have never matches.
+//!
+//! Rules are tried in order and the first that matches decides; the model is only consulted when
+//! none does.
+
+use crate::error::Error;
+use crate::policy::Decision;
+use crate::subject::Subject;
+use crate::types::Notification;
+
+//////////////////////////////////////////////// Field /////////////////////////////////////////////
+
+/// A property of a notification that rules can test
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Field {
+    Reason,
+    Type,
+    Repo,
+    Owner,
+    Title,
+    Unread,
+    Author,
+    State,
+    Labels,
+    Draft,
+    Merged,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "reason" => Self::Reason,
+            "type" => Self::Type,
+            "repo" => Self::Repo,
+            "owner" => Self::Owner,
+            "title" => Self::Title,
+            "unread" => Self::Unread,
+            "author" => Self::Author,
+            "state" => Self::State,
+            "labels" => Self::Labels,
+            "draft" => Self::Draft,
+            "merged" => Self::Merged,
+            _ => return None,
+        })
+    }
+
+    fn is_bool(self) -> bool {
+        matches!(self, Self::Unread | Self::Draft | Self::Merged)
+    }
+
+    fn value(self, thread: &Notification, subject: Option<&Subject>) -> Value {
+        let pr = match subject {
+            Some(Subject::PullRequest(pr)) => Some(pr),
+            _ => None,
+        };
+        match self {
+            Self::Reason => Value::Str(thread.reason.to_string()),
+            Self::Type => Value::Str(thread.subject.r#type.to_string()),
+            Self::Repo => Value::Str(thread.repository.full_name.clone()),
+            Self::Owner => Value::Str(thread.repository.owner.login.clone()),
+            Self::Title => Value::Str(thread.subject.title.clone()),
+            Self::Unread => Value::Bool(thread.unread),
+            Self::Author => match subject {
+                Some(Subject::PullRequest(pr)) => Value::Str(pr.user.login.clone()),
+                Some(Subject::Issue(issue)) => issue
+                    .user
+                    .as_ref()
+                    .map(|user| Value::Str(user.login.clone()))
+                    .unwrap_or(Value::Missing),
+                Some(Subject::Commit { commit, .. }) => commit
+                    .author
+                    .as_ref()
+                    .map(|user| Value::Str(user.login.clone()))
+                    .unwrap_or(Value::Missing),
+                Some(Subject::Release(release)) => Value::Str(release.author.login.clone()),
+                Some(Subject::Discussion(discussion)) => discussion
+                    .author
+                    .as_ref()
+                    .map(|author| Value::Str(author.login.clone()))
+                    .unwrap_or(Value::Missing),
+                Some(Subject::WorkflowRun { run, .. }) => run
+                    .actor
+                    .as_ref()
+                    .map(|user| Value::Str(user.login.clone()))
+                    .unwrap_or(Value::Missing),
+                Some(Subject::SecurityAlerts(_)) | None => Value::Missing,
+            },
+            Self::State => match subject {
+                Some(Subject::PullRequest(pr)) => Value::Str(pr.state.to_string()),
+                Some(Subject::Issue(issue)) => Value::Str(issue.state.to_string()),
+                Some(Subject::Discussion(discussion)) => {
+                    Value::Str(if discussion.closed { "closed" } else { "open" }.to_string())
+                }
+                Some(Subject::WorkflowRun { run, .. }) => match (&run.conclusion, &run.status) {
+                    (Some(conclusion), _) => Value::Str(conclusion.to_string()),
+                    (None, Some(status)) => Value::Str(status.to_string()),
+                    (None, None) => Value::Missing,
+                },
+                _ => Value::Missing,
+            },
+            Self::Labels => match subject {
+                Some(Subject::PullRequest(pr)) => {
+                    Value::List(pr.labels.iter().map(|l| l.name().to_string()).collect())
+                }
+                Some(Subject::Issue(issue)) => {
+                    Value::List(issue.labels.iter().map(|l| l.name().to_string()).collect())
+                }
+                Some(Subject::Discussion(discussion)) => Value::List(discussion.label_names()),
+                _ => Value::Missing,
+            },
+            Self::Draft => match subject {
+                Some(Subject::PullRequest(pr)) => Value::Bool(pr.draft.unwrap_or(false)),
+                Some(Subject::Release(release)) => Value::Bool(release.draft),
+                _ => Value::Missing,
+            },
+            Self::Merged => pr
+                .map(|pr| Value::Bool(pr.merged.unwrap_or(false) || pr.merged_at.is_some()))
+                .unwrap_or(Value::Missing),
+        }
+    }
+}
+
+enum Value {
+    Str(String),
+    Bool(bool),
+    List(Vec<String>),
+    Missing,
+}
+
+///////////////////////////////////////////////// Expr /////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Glob,
+    NotGlob,
+}
+
+impl Op {
+    fn holds(self, actual: &str, expected: &str) -> bool {
+        match self {
+            Op::Eq => actual == expected,
+            Op::Ne => actual != expected,
+            Op::Glob => glob(expected, actual),
+            Op::NotGlob => !glob(expected, actual),
+        }
+    }
+}
+
+/// A parsed rule condition
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expr {
+    Test(Field),
+    Compare { field: Field, op: Op, value: String },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    pub fn eval(&self, thread: &Notification, subject: Option<&Subject>) -> bool {
+        match self {
+            Expr::Test(field) => matches!(field.value(thread, subject), Value::Bool(true)),
+            Expr::Compare { field, op, value } => match field.value(thread, subject) {
+                Value::Str(actual) => op.holds(&actual, value),
+                Value::List(items) => match op {
+                    Op::Eq | Op::Glob => items.iter().any(|item| op.holds(item, value)),
+                    Op::Ne => items.iter().all(|item| item != value),
+                    Op::NotGlob => items.iter().all(|item| !glob(value, item)),
+                },
+                Value::Bool(_) | Value::Missing => false,
+            },
+            Expr::Not(expr) => !expr.eval(thread, subject),
+            Expr::And(lhs, rhs) => lhs.eval(thread, subject) && rhs.eval(thread, subject),
+            Expr::Or(lhs, rhs) => lhs.eval(thread, subject) || rhs.eval(thread, subject),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Op(Op::Eq) => f.write_str("`==`"),
+            Token::Op(Op::Ne) => f.write_str("`!=`"),
+            Token::Op(Op::Glob) => f.write_str("`~`"),
+            Token::Op(Op::NotGlob) => f.write_str("`!~`"),
+            Token::Open => f.write_str("`(`"),
+            Token::Close => f.write_str("`)`"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '~' => tokens.push(Token::Op(Op::Glob)),
+            '=' if chars.next_if_eq(&'=').is_some() => tokens.push(Token::Op(Op::Eq)),
+            '!' if chars.next_if_eq(&'=').is_some() => tokens.push(Token::Op(Op::Ne)),
+            '!' if chars.next_if_eq(&'~').is_some() => tokens.push(Token::Op(Op::NotGlob)),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => s.push(escaped),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(c) => s.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(format!("unexpected character {:?}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(token) => Err(format!("expected `)`, found {}", token)),
+                    None => Err("expected `)`".to_string()),
+                }
+            }
+            Some(Token::Ident(name)) => {
+                let field =
+                    Field::parse(&name).ok_or_else(|| format!("unknown field `{}`", name))?;
+                let Some(Token::Op(op)) = self.peek().cloned() else {
+                    return if field.is_bool() {
+                        Ok(Expr::Test(field))
+                    } else {
+                        Err(format!("`{}` needs a comparison", name))
+                    };
+                };
+                if field.is_bool() {
+                    return Err(format!("`{}` is a test and cannot be compared", name));
+                }
+                self.pos += 1;
+                match self.next() {
+                    Some(Token::Str(value)) => Ok(Expr::Compare { field, op, value }),
+                    Some(token) => Err(format!("expected a quoted string, found {}", token)),
+                    None => Err("expected a quoted string".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of rule".to_string()),
+        }
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters and `?` any one
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // Where the most recent `*` was, and how much of the text it has swallowed.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+///////////////////////////////////////////////// Rule /////////////////////////////////////////////
+
+/// A condition and the decision it makes
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    /// The rule as written, for reporting
+    pub source: String,
+    pub condition: Expr,
+    pub decision: Decision,
+}
+
+impl Rule {
+    /// Whether a policy line's text, as split off by the parser, is a rule
+    pub fn is_rule(text: &str) -> bool {
+        text.split_whitespace().next() == Some("when")
+    }
+
+    /// Parse a rule from a policy line's text and the decision that follows it
+    pub fn parse(text: &str, decision: serde_json::Value) -> Result<Self, Error> {
+        let invalid = |message: String| Error::InvalidRule {
+            rule: text.to_string(),
+            message,
+        };
+        let condition = text
+            .trim()
+            .strip_prefix("when")
+            .and_then(|rest| rest.trim_end().strip_suffix("output"))
+            .ok_or_else(|| invalid("expected `when CONDITION output {...}`".to_string()))?;
+        Ok(Self {
+            source: text.to_string(),
+            condition: Expr::parse(condition).map_err(invalid)?,
+            decision: Decision::from_value(decision)?,
+        })
+    }
+
+    pub fn matches(&self, thread: &Notification, subject: Option<&Subject>) -> bool {
+        self.condition.eval(thread, subject)
+    }
+}
+
+/// The first of `rules` that matches the notification
+pub fn first_match<'a>(
+    rules: &'a [Rule],
+    thread: &Notification,
+    subject: Option<&Subject>,
+) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.matches(thread, subject))
+}
+
+/////////////////////////////////////////////// tests //////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_thread;
+    use crate::policy::DecisionAction;
+
+    fn pull_request() -> Subject {
+        Subject::PullRequest(
+            serde_json::from_str(include_str!("../tests/fixtures/pull_request.json")).unwrap(),
+        )
+    }
+
+    fn rule(line: &str) -> Rule {
+        let (text, decision) = crate::parser::parse_text_json(line).unwrap();
+        Rule::parse(&text, decision).unwrap()
+    }
+
+    #[test]
+    fn glob_matches() {
+        assert!(glob("rescrv/*", "rescrv/ghai"));
+        assert!(glob("*[bot]", "dependabot[bot]"));
+        assert!(glob("r?scrv/*i", "rescrv/ghai"));
+        assert!(glob("*", ""));
+        assert!(!glob("rescrv/*", "octocat/ghai"));
+        assert!(!glob("a*b", "aab c"));
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let expr = Expr::parse(r#"not draft and repo == "a" or unread"#).unwrap();
+        assert_eq!(
+            Expr::Or(
+                Box::new(Expr::And(
+                    Box::new(Expr::Not(Box::new(Expr::Test(Field::Draft)))),
+                    Box::new(Expr::Compare {
+                        field: Field::Repo,
+                        op: Op::Eq,
+                        value: "a".to_string(),
+                    }),
+                )),
+                Box::new(Expr::Test(Field::Unread)),
+            ),
+            expr
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (input, message) in [
+            ("colour == \"red\"", "unknown field `colour`"),
+            ("repo", "`repo` needs a comparison"),
+            (
+                "merged == \"true\"",
+                "`merged` is a test and cannot be compared",
+            ),
+            ("repo == rescrv", "expected a quoted string, found `rescrv`"),
+            ("(unread", "expected `)`"),
+            ("repo == \"a", "unterminated string"),
+            ("unread unread", "unexpected `unread`"),
+        ] {
+            assert_eq!(Err(message.to_string()), Expr::parse(input), "{}", input);
+        }
+        assert!(matches!(
+            Rule::parse("when unread", serde_json::json!({})),
+            Err(Error::InvalidRule { .. })
+        ));
+    }
+
+    #[test]
+    fn rules_match_notification_fields() {
+        let pr_thread = fixture_thread("notifications_page1.json", 0);
+        assert!(rule(r#"when reason == "review_requested" output {}"#).matches(&pr_thread, None));
+        assert!(rule(r#"when repo ~ "rescrv/*" and unread output {}"#).matches(&pr_thread, None));
+        assert!(!rule(r#"when type != "PullRequest" output {}"#).matches(&pr_thread, None));
+        // Subject fields never match without a subject, however they are compared.
+        assert!(!rule(r#"when author != "nobody" output {}"#).matches(&pr_thread, None));
+        assert!(!rule("when merged output {}").matches(&pr_thread, None));
+        assert!(rule("when not merged output {}").matches(&pr_thread, None));
+    }
+
+    #[test]
+    fn rules_match_subject_fields() {
+        let pr_thread = fixture_thread("notifications_page1.json", 0);
+        let pr = pull_request();
+        let Subject::PullRequest(fetched) = &pr else {
+            unreachable!()
+        };
+        let author = format!(r#"when author == "{}" output {{}}"#, fetched.user.login);
+        assert!(rule(&author).matches(&pr_thread, Some(&pr)));
+        assert!(
+            rule(r#"when state == "open" and not merged output {}"#).matches(&pr_thread, Some(&pr))
+        );
+        assert!(rule(r#"when labels !~ "wontfix*" output {}"#).matches(&pr_thread, Some(&pr)));
+    }
+
+    #[test]
+    fn rules_match_release_fields() {
+        let release = Subject::Release(
+            serde_json::from_str(include_str!("../tests/fixtures/release.json")).unwrap(),
+        );
+        let thread = fixture_thread("notifications_page1.json", 0);
+        assert!(rule(r#"when author == "rescrv" and not draft output {}"#)
+            .matches(&thread, Some(&release)));
+        // Releases have no state or labels.
+        assert!(!rule(r#"when state != "open" output {}"#).matches(&thread, Some(&release)));
+        assert!(!rule(r#"when labels ~ "*" output {}"#).matches(&thread, Some(&release)));
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let rules = vec![
+            rule(r#"when type == "Issue" output {"action": "mark-unread"}"#),
+            rule(r#"when repo == "rescrv/ghai" output {"action": "mark-done"}"#),
+            rule(r#"when unread output {"action": "mark-read"}"#),
+        ];
+        let decided =
+            first_match(&rules, &fixture_thread("notifications_page1.json", 0), None).unwrap();
+        assert_eq!(Some(DecisionAction::MarkDone), decided.decision.action);
+        assert_eq!(
+            Some(DecisionAction::MarkUnread),
+            first_match(&rules, &fixture_thread("notifications_page1.json", 1), None)
+                .unwrap()
+                .decision
+                .action
+        );
+        assert!(first_match(
+            &rules[..1],
+            &fixture_thread("notifications_page1.json", 0),
+            None
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,64 @@
+//! What a notification is about.
+//!
+//! A notification names its subject only by type, title, and (for most types) a REST URL.
+//! [`Subject::fetch`] fetches the subject itself, so that rules and policies can look at it.
+
+use crate::error::Error;
+use crate::http::GitHubClient;
+use crate::types::{
+    Action, CommitComment, DependabotAlert, Discussion, Issue, Job, Notification, PullRequest,
+    Release, RepositoryCommit, SubjectType,
+};
+
+/////////////////////////////////////////////// Subject ////////////////////////////////////////////
+
+/// The subject of a notification, when it has been fetched
+#[allow(clippy::large_enum_variant)]
+pub enum Subject {
+    PullRequest(PullRequest),
+    Issue(Issue),
+    Commit {
+        commit: RepositoryCommit,
+        comments: Vec<CommitComment>,
+    },
+    Release(Release),
+    Discussion(Discussion),
+    /// The workflow run a check suite notification reports on, and its jobs
+    WorkflowRun {
+        run: Action,
+        jobs: Vec<Job>,
+    },
+    /// The open alerts a security alert notification may be about
+    SecurityAlerts(Vec<DependabotAlert>),
+}
+
+impl Subject {
+    /// Fetch the subject of `thread`, or `None` if ghai does not support its type
+    pub async fn fetch(
+        thread: &Notification,
+        client: &GitHubClient,
+    ) -> Result<Option<Self>, Error> {
+        Ok(Some(match &thread.subject.r#type {
+            SubjectType::PullRequest => {
+                Subject::PullRequest(thread.fetch_pull_request(client).await?)
+            }
+            SubjectType::Issue => Subject::Issue(thread.fetch_issue(client).await?),
+            SubjectType::Commit => Subject::Commit {
+                commit: thread.fetch_commit(client).await?,
+                comments: thread.fetch_commit_comments(client).await?,
+            },
+            SubjectType::Release => Subject::Release(thread.fetch_release(client).await?),
+            SubjectType::Discussion => Subject::Discussion(thread.fetch_discussion(client).await?),
+            SubjectType::CheckSuite => {
+                let run = thread.fetch_workflow_run(client).await?;
+                let jobs = run.fetch_jobs(client).await?;
+                Subject::WorkflowRun { run, jobs }
+            }
+            SubjectType::RepositoryVulnerabilityAlert
+            | SubjectType::RepositoryDependabotAlertsThread => {
+                Subject::SecurityAlerts(thread.fetch_security_alerts(client).await?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
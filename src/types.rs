@@ -1,5 +1,6 @@
-use crate::http::{GitHubClient, UrlBuilder};
-use chrono::{DateTime, TimeZone};
+use crate::error::Error;
+use crate::http::{Conditional, GitHubClient, Page, UrlBuilder};
+use chrono::{DateTime, TimeZone, Utc};
 
 /// Trait for types that can fetch comments
 pub trait CommentFetcher: Sync {
@@ -7,8 +8,9 @@ pub trait CommentFetcher: Sync {
 
     fn fetch_comments<Tz: TimeZone>(
         &self,
+        client: &GitHubClient,
         since: Option<DateTime<Tz>>,
-    ) -> impl std::future::Future<Output = Result<Vec<IssueComment>, Box<dyn std::error::Error>>> + Send
+    ) -> impl std::future::Future<Output = Result<Vec<IssueComment>, Error>> + Send
     where
         Tz::Offset: Send,
     {
@@ -17,85 +19,95 @@ pub trait CommentFetcher: Sync {
                 .param("since", since.map(|s| s.to_rfc3339()))
                 .build();
 
-            let client = GitHubClient::new()?;
-            let response = client
-                .get(&url)
-                .send()
-                .await?
-                .json::<Vec<IssueComment>>()
-                .await?;
-
-            Ok(response)
+            client.paginate::<Vec<IssueComment>>(&url, None).await
         }
     }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssueDependenciesSummary {
     pub blocked_by: i64,
     pub blocking: i64,
     pub total_blocked_by: i64,
     pub total_blocking: i64,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssueFieldValueSingleSelectOption {
     pub id: i64,
     pub name: String,
     pub color: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssueFieldValue {
     pub issue_field_id: i64,
     pub node_id: String,
     pub data_type: String,
     pub value: Option<serde_json::Value>,
     pub single_select_option: Option<IssueFieldValueSingleSelectOption>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssueType {
     pub id: i64,
     pub node_id: String,
     pub name: String,
     pub description: Option<String>,
     pub color: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub is_enabled: bool,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Commit {
     pub id: String,
     pub tree_id: String,
     pub distinct: bool,
     pub message: String,
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
     pub url: String,
     pub author: CommitUser,
     pub committer: CommitUser,
     pub added: Vec<String>,
     pub removed: Vec<String>,
     pub modified: Vec<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct CommitUser {
     pub name: String,
     pub email: String,
     pub username: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Repository {
     pub id: i64,
     pub node_id: String,
@@ -167,9 +179,9 @@ pub struct Repository {
     pub archived: Option<bool>,
     pub disabled: Option<bool>,
     pub visibility: Option<String>,
-    pub pushed_at: Option<String>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
     pub permissions: Option<RepositoryPermissions>,
     pub allow_rebase_merge: Option<bool>,
     pub template_repository: Option<serde_json::Value>,
@@ -186,30 +198,39 @@ pub struct Repository {
     pub forks: Option<u64>,
     pub open_issues: Option<u64>,
     pub watchers: Option<u64>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct RepositoryPermissions {
     pub admin: bool,
     pub maintain: Option<bool>,
     pub push: bool,
     pub pull: bool,
     pub triage: Option<bool>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct RepositoryLicense {
     pub key: String,
     pub name: String,
     pub spdx_id: Option<String>,
     pub url: Option<String>,
     pub node_id: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Milestone {
     pub url: String,
     pub html_url: String,
@@ -223,24 +244,30 @@ pub struct Milestone {
     pub open_issues: u64,
     pub closed_issues: u64,
     pub state: String,
-    pub created_at: String,
-    pub updated_at: String,
-    pub due_on: Option<String>,
-    pub closed_at: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub due_on: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssuePullRequest {
     pub url: String,
     pub html_url: String,
     pub diff_url: String,
     pub patch_url: String,
-    pub merged_at: Option<String>,
+    pub merged_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Integration {
     pub id: i64,
     pub slug: Option<String>,
@@ -250,8 +277,8 @@ pub struct Integration {
     pub description: Option<String>,
     pub external_url: String,
     pub html_url: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub permissions: IntegrationPermissions,
     pub events: Vec<String>,
     pub installations_count: Option<u64>,
@@ -259,20 +286,97 @@ pub struct Integration {
     pub client_secret: Option<String>,
     pub webhook_secret: Option<String>,
     pub pem: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IntegrationPermissions {
     pub issues: Option<String>,
     pub checks: Option<String>,
     pub metadata: Option<String>,
     pub contents: Option<String>,
     pub deployments: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+string_enum! {
+    /// Whether an issue or pull request is open or closed
+    pub enum IssueState {
+        Open => "open",
+        Closed => "closed",
+    }
+}
+
+string_enum! {
+    /// Why the user received a notification
+    pub enum NotificationReason {
+        ApprovalRequested => "approval_requested",
+        Assign => "assign",
+        Author => "author",
+        CiActivity => "ci_activity",
+        Comment => "comment",
+        Invitation => "invitation",
+        Manual => "manual",
+        MemberFeatureRequested => "member_feature_requested",
+        Mention => "mention",
+        ReviewRequested => "review_requested",
+        SecurityAdvisoryCredit => "security_advisory_credit",
+        SecurityAlert => "security_alert",
+        StateChange => "state_change",
+        Subscribed => "subscribed",
+        TeamMention => "team_mention",
+    }
+}
+
+string_enum! {
+    /// The kind of thing a notification is about
+    pub enum SubjectType {
+        Issue => "Issue",
+        PullRequest => "PullRequest",
+        Commit => "Commit",
+        Release => "Release",
+        Discussion => "Discussion",
+        CheckSuite => "CheckSuite",
+        RepositoryVulnerabilityAlert => "RepositoryVulnerabilityAlert",
+        RepositoryDependabotAlertsThread => "RepositoryDependabotAlertsThread",
+        RepositoryInvitation => "RepositoryInvitation",
+    }
+}
+
+string_enum! {
+    /// Progress of a workflow run, job, or job step
+    pub enum RunStatus {
+        Requested => "requested",
+        Queued => "queued",
+        Pending => "pending",
+        Waiting => "waiting",
+        InProgress => "in_progress",
+        Completed => "completed",
+    }
+}
+
+string_enum! {
+    /// Outcome of a completed workflow run, job, or job step
+    pub enum RunConclusion {
+        Success => "success",
+        Failure => "failure",
+        Neutral => "neutral",
+        Cancelled => "cancelled",
+        Skipped => "skipped",
+        TimedOut => "timed_out",
+        ActionRequired => "action_required",
+        Stale => "stale",
+        StartupFailure => "startup_failure",
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AuthorAssociation {
     Owner,
@@ -286,7 +390,7 @@ pub enum AuthorAssociation {
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct ReactionRollup {
     pub url: String,
     pub total_count: u64,
@@ -300,21 +404,28 @@ pub struct ReactionRollup {
     pub heart: u64,
     pub rocket: u64,
     pub eyes: u64,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct SubIssuesSummary {
     pub completed: u64,
     pub percent_completed: u64,
     pub total: u64,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Links {
+    /// Absent on reviews
     #[serde(rename = "self")]
-    pub self_link: Link,
+    pub self_link: Option<Link>,
     pub html: Link,
     pub issue: Option<Link>,
     pub comments: Option<Link>,
@@ -322,10 +433,15 @@ pub struct Links {
     pub review_comment: Option<Link>,
     pub commits: Option<Link>,
     pub statuses: Option<Link>,
+    /// Present on reviews and review comments
+    pub pull_request: Option<Link>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Team {
     pub id: i64,
     pub node_id: String,
@@ -339,33 +455,42 @@ pub struct Team {
     pub members_url: String,
     pub repositories_url: String,
     pub parent: Option<Box<Team>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct AutoMerge {
     pub enabled_by: SimpleUser,
     pub merge_method: String,
     pub commit_title: Option<String>,
     pub commit_message: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct HeadCommit {
     pub id: String,
     pub tree_id: String,
     pub message: String,
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
     pub author: CommitUser,
     pub committer: CommitUser,
     pub added: Vec<String>,
     pub removed: Vec<String>,
     pub modified: Vec<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct SimpleUser {
     pub name: Option<String>,
     pub email: Option<String>,
@@ -387,12 +512,15 @@ pub struct SimpleUser {
     pub received_events_url: String,
     pub r#type: String,
     pub site_admin: bool,
-    pub starred_at: Option<String>,
+    pub starred_at: Option<DateTime<Utc>>,
     pub user_view_type: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Actor {
     pub id: i64,
     pub login: String,
@@ -400,18 +528,24 @@ pub struct Actor {
     pub gravatar_id: Option<String>,
     pub url: String,
     pub avatar_url: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct RepoStub {
     pub id: u64,
     pub name: String,
     pub url: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 #[serde(tag = "type")]
 pub struct Event {
     pub r#type: String,
@@ -421,11 +555,14 @@ pub struct Event {
     pub org: Option<Actor>,
     pub payload: serde_json::Value,
     pub public: bool,
-    pub created_at: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 #[serde(untagged)]
 pub enum EventPayload {
     IssueComment {
@@ -483,7 +620,7 @@ pub enum EventPayload {
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 #[serde(untagged)]
 pub enum Label {
     Detailed {
@@ -508,13 +645,16 @@ impl Label {
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Link {
     pub href: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Issue {
     pub id: i64,
     pub node_id: String,
@@ -525,7 +665,7 @@ pub struct Issue {
     pub events_url: String,
     pub html_url: String,
     pub number: u64,
-    pub state: String,
+    pub state: IssueState,
     pub state_reason: Option<String>,
     pub title: String,
     pub body: Option<String>,
@@ -538,9 +678,9 @@ pub struct Issue {
     pub active_lock_reason: Option<String>,
     pub comments: u64,
     pub pull_request: Option<IssuePullRequest>,
-    pub closed_at: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub draft: Option<bool>,
     pub closed_by: Option<SimpleUser>,
     pub body_html: Option<String>,
@@ -556,11 +696,17 @@ pub struct Issue {
     pub parent_issue_url: Option<String>,
     #[serde(rename = "type")]
     pub r#type: Option<IssueType>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 impl Issue {
+    /// Fetch the issues assigned to the authenticated user, starting at `page` and following
+    /// pagination until `limit` issues have been collected or the results are exhausted.
     #[allow(clippy::too_many_arguments)]
     pub async fn fetch_user_issues<Tz: TimeZone>(
+        client: &GitHubClient,
         filter: Option<String>,
         state: Option<String>,
         labels: Option<String>,
@@ -569,8 +715,9 @@ impl Issue {
         since: Option<DateTime<Tz>>,
         per_page: Option<u64>,
         page: Option<u64>,
-    ) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
-        let url = UrlBuilder::new("https://api.github.com/issues")
+        limit: Option<usize>,
+    ) -> Result<Vec<Issue>, Error> {
+        let url = UrlBuilder::new(client.api_url("/issues"))
             .param("filter", filter)
             .param("state", state)
             .param("labels", labels)
@@ -581,10 +728,7 @@ impl Issue {
             .param("page", page)
             .build();
 
-        let client = GitHubClient::new()?;
-        let issues: Vec<Issue> = client.get(&url).send().await?.json().await?;
-
-        Ok(issues)
+        client.paginate::<Vec<Issue>>(&url, limit).await
     }
 }
 
@@ -595,7 +739,7 @@ impl CommentFetcher for Issue {
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IssueComment {
     pub id: i64,
     pub node_id: String,
@@ -605,16 +749,19 @@ pub struct IssueComment {
     pub body_html: Option<String>,
     pub html_url: String,
     pub user: Option<SimpleUser>,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub issue_url: String,
     pub author_association: AuthorAssociation,
     pub performed_via_github_app: Option<Integration>,
     pub reactions: Option<ReactionRollup>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct PullRequestReview {
     pub _links: Option<Links>,
     pub author_association: String,
@@ -625,12 +772,17 @@ pub struct PullRequestReview {
     pub node_id: String,
     pub pull_request_url: String,
     pub state: String,
-    pub submitted_at: String,
-    pub user: SimpleUser,
+    /// `None` while the review is pending
+    pub submitted_at: Option<DateTime<Utc>>,
+    /// `None` when the reviewer's account has been deleted
+    pub user: Option<SimpleUser>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct PullRequestReviewComment {
     pub id: i64,
     pub node_id: String,
@@ -640,8 +792,8 @@ pub struct PullRequestReviewComment {
     pub body_html: Option<String>,
     pub html_url: String,
     pub user: Option<SimpleUser>,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub issue_url: Option<String>,
     pub author_association: AuthorAssociation,
     pub performed_via_github_app: Option<Integration>,
@@ -663,10 +815,13 @@ pub struct PullRequestReviewComment {
     pub start_side: Option<String>,
     pub subject_type: Option<String>,
     pub in_reply_to_id: Option<serde_json::Value>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct PullRequestHead {
     pub label: String,
     #[serde(rename = "ref")]
@@ -674,10 +829,13 @@ pub struct PullRequestHead {
     pub sha: String,
     pub user: SimpleUser,
     pub repo: Repository,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct PullRequest {
     pub url: String,
     pub id: i64,
@@ -692,7 +850,7 @@ pub struct PullRequest {
     pub comments_url: String,
     pub statuses_url: String,
     pub number: u64,
-    pub state: String,
+    pub state: IssueState,
     pub locked: bool,
     pub title: String,
     pub user: SimpleUser,
@@ -700,10 +858,10 @@ pub struct PullRequest {
     pub labels: Vec<Label>,
     pub milestone: Option<Milestone>,
     pub active_lock_reason: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
-    pub closed_at: Option<String>,
-    pub merged_at: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub merged_at: Option<DateTime<Utc>>,
     pub merge_commit_sha: Option<String>,
     pub assignee: Option<SimpleUser>,
     pub assignees: Option<Vec<SimpleUser>>,
@@ -727,6 +885,9 @@ pub struct PullRequest {
     pub additions: Option<u64>,
     pub deletions: Option<u64>,
     pub changed_files: Option<u64>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 impl CommentFetcher for PullRequest {
@@ -735,37 +896,94 @@ impl CommentFetcher for PullRequest {
     }
 }
 
+impl PullRequest {
+    /// The submitted reviews of this pull request, oldest first; only those submitted after
+    /// `since`, when it is given
+    pub async fn fetch_reviews<Tz: TimeZone>(
+        &self,
+        client: &GitHubClient,
+        since: Option<DateTime<Tz>>,
+    ) -> Result<Vec<PullRequestReview>, Error> {
+        let url = format!("{}/reviews", self.url);
+        let reviews = client
+            .paginate::<Vec<PullRequestReview>>(&url, None)
+            .await?;
+        let since = since.map(|s| s.with_timezone(&Utc));
+        Ok(reviews
+            .into_iter()
+            .filter(|review| match (review.submitted_at, since) {
+                (None, _) => false,
+                (Some(submitted), Some(since)) => submitted > since,
+                (Some(_), None) => true,
+            })
+            .collect())
+    }
+
+    /// The files this pull request changes, with their patches where GitHub provides them
+    ///
+    /// GitHub lists at most 3000 files.
+    pub async fn fetch_files(&self, client: &GitHubClient) -> Result<Vec<DiffEntry>, Error> {
+        let url = UrlBuilder::new(format!("{}/files", self.url))
+            .required_param("per_page", 100)
+            .build();
+        client.paginate::<Vec<DiffEntry>>(&url, None).await
+    }
+
+    /// The inline review comments of this pull request, oldest first; only those updated after
+    /// `since`, when it is given
+    pub async fn fetch_review_comments<Tz: TimeZone>(
+        &self,
+        client: &GitHubClient,
+        since: Option<DateTime<Tz>>,
+    ) -> Result<Vec<PullRequestReviewComment>, Error> {
+        let url = UrlBuilder::new(&self.review_comments_url)
+            .param("since", since.map(|s| s.to_rfc3339()))
+            .build();
+        client
+            .paginate::<Vec<PullRequestReviewComment>>(&url, None)
+            .await
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct NotificationSubject {
     pub title: String,
-    pub url: String,
+    /// The REST URL of the subject; `None` for discussions and check suites, which have none
+    pub url: Option<String>,
     pub latest_comment_url: Option<String>,
-    pub r#type: String,
+    pub r#type: SubjectType,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Notification {
     pub id: String,
     pub unread: bool,
-    pub reason: String,
-    pub updated_at: String,
-    pub last_read_at: Option<String>,
+    pub reason: NotificationReason,
+    pub updated_at: DateTime<Utc>,
+    pub last_read_at: Option<DateTime<Utc>>,
     pub subject: NotificationSubject,
     pub repository: Repository,
     pub url: String,
     pub subscription_url: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 impl Notification {
     pub async fn fetch_all<Tz: chrono::TimeZone>(
+        client: &GitHubClient,
         all: bool,
         participating: bool,
         since: Option<DateTime<Tz>>,
         before: Option<DateTime<Tz>>,
-    ) -> Result<Vec<Notification>, Box<dyn std::error::Error>> {
-        let url = UrlBuilder::new("https://api.github.com/notifications")
+    ) -> Result<Vec<Notification>, Error> {
+        let url = UrlBuilder::new(client.api_url("/notifications"))
             .param("all", if all { Some("true") } else { None })
             .param(
                 "participating",
@@ -775,59 +993,788 @@ impl Notification {
             .param("before", before.map(|s| s.to_rfc3339()))
             .build();
 
-        let client = GitHubClient::new()?;
-        let mut notifications: Vec<Notification> = client.get(&url).send().await?.json().await?;
+        let mut notifications = client.paginate::<Vec<Notification>>(&url, None).await?;
 
-        notifications.sort_by_key(|n| n.updated_at.clone());
+        notifications.sort_by_key(|n| n.updated_at);
         Ok(notifications)
     }
 
-    pub async fn fetch_pull_request(&self) -> Result<PullRequest, Box<dyn std::error::Error>> {
-        if self.subject.r#type != "PullRequest" {
-            return Err("not a pull request".into());
+    /// Fetch the unread notifications unless none have changed since `if_modified_since`
+    ///
+    /// This is the polling GitHub recommends: feed each result's `last_modified` into the next
+    /// call and wait at least its `poll_interval` in between.
+    pub async fn poll(
+        client: &GitHubClient,
+        if_modified_since: Option<&str>,
+    ) -> Result<Conditional<Notification>, Error> {
+        let url = client.api_url("/notifications");
+        let mut poll = client
+            .paginate_if_modified::<Vec<Notification>>(&url, if_modified_since)
+            .await?;
+        if let Some(notifications) = poll.items.as_mut() {
+            notifications.sort_by_key(|n| n.updated_at);
+        }
+        Ok(poll)
+    }
+
+    pub async fn fetch_pull_request(&self, client: &GitHubClient) -> Result<PullRequest, Error> {
+        self.expect_subject(&[SubjectType::PullRequest])?;
+        client.get_json(self.subject_url()?).await
+    }
+
+    /// The web page for the notification's subject, or the closest page we can name without
+    /// fetching it
+    pub fn html_url(&self) -> String {
+        let repo = &self.repository.html_url;
+        match &self.subject.r#type {
+            SubjectType::PullRequest => format!("{}/pull/{}", repo, self.subject_number()),
+            SubjectType::Issue => format!("{}/issues/{}", repo, self.subject_number()),
+            SubjectType::Commit => format!("{}/commit/{}", repo, self.subject_number()),
+            SubjectType::Release => format!("{}/releases", repo),
+            SubjectType::Discussion => format!("{}/discussions", repo),
+            SubjectType::CheckSuite => format!("{}/actions", repo),
+            SubjectType::RepositoryVulnerabilityAlert
+            | SubjectType::RepositoryDependabotAlertsThread => {
+                format!("{}/security/dependabot", repo)
+            }
+            _ => repo.clone(),
+        }
+    }
+
+    /// The subject's REST URL, which every subject type but discussions and check suites has
+    fn subject_url(&self) -> Result<&str, Error> {
+        self.subject.url.as_deref().ok_or_else(|| {
+            Error::MissingSubject(format!(
+                "the {} notification '{}' has no subject URL",
+                self.subject.r#type, self.subject.title
+            ))
+        })
+    }
+
+    /// The last segment of the subject URL: the number of an issue or pull request, the SHA of a
+    /// commit, or the id of a release
+    fn subject_number(&self) -> &str {
+        self.subject
+            .url
+            .as_deref()
+            .and_then(|url| url.rsplit('/').next())
+            .unwrap_or_default()
+    }
+
+    fn expect_subject(&self, expected: &[SubjectType]) -> Result<(), Error> {
+        if expected.contains(&self.subject.r#type) {
+            return Ok(());
         }
+        Err(Error::UnexpectedSubject {
+            expected: expected
+                .iter()
+                .map(SubjectType::to_string)
+                .collect::<Vec<_>>()
+                .join(" or "),
+            actual: self.subject.r#type.to_string(),
+        })
+    }
+
+    /// Fetch the commit a `Commit` notification is about, with its files and stats
+    pub async fn fetch_commit(&self, client: &GitHubClient) -> Result<RepositoryCommit, Error> {
+        self.expect_subject(&[SubjectType::Commit])?;
+        client.get_json(self.subject_url()?).await
+    }
 
-        let client = GitHubClient::new()?;
-        let pull_request: PullRequest = client.get(&self.subject.url).send().await?.json().await?;
+    /// The comments on the commit a `Commit` notification is about, oldest first
+    pub async fn fetch_commit_comments(
+        &self,
+        client: &GitHubClient,
+    ) -> Result<Vec<CommitComment>, Error> {
+        self.expect_subject(&[SubjectType::Commit])?;
+        let url = format!("{}/comments", self.subject_url()?);
+        client.paginate::<Vec<CommitComment>>(&url, None).await
+    }
 
-        Ok(pull_request)
+    pub async fn fetch_release(&self, client: &GitHubClient) -> Result<Release, Error> {
+        self.expect_subject(&[SubjectType::Release])?;
+        client.get_json(self.subject_url()?).await
     }
 
-    pub async fn fetch_issue(&self) -> Result<Issue, Box<dyn std::error::Error>> {
-        if self.subject.r#type != "Issue" {
-            return Err("not an issue".into());
+    /// Find the discussion a `Discussion` notification is about
+    ///
+    /// Discussion notifications carry no URL, so the repository's most recently updated
+    /// discussions are searched, via GraphQL, for one with the notification's title.
+    pub async fn fetch_discussion(&self, client: &GitHubClient) -> Result<Discussion, Error> {
+        self.expect_subject(&[SubjectType::Discussion])?;
+        #[derive(serde::Deserialize)]
+        struct Data {
+            repository: Option<DiscussionsRepository>,
+        }
+        #[derive(serde::Deserialize)]
+        struct DiscussionsRepository {
+            discussions: Nodes<Discussion>,
         }
+        let data: Data = client
+            .graphql(
+                DISCUSSIONS_QUERY,
+                serde_json::json!({
+                    "owner": self.repository.owner.login,
+                    "name": self.repository.name,
+                }),
+            )
+            .await?;
+        data.repository
+            .into_iter()
+            .flat_map(|repo| repo.discussions.nodes)
+            .find(|discussion| discussion.title == self.subject.title)
+            .ok_or_else(|| {
+                Error::MissingSubject(format!(
+                    "no recently updated discussion in {} is titled '{}'",
+                    self.repository.full_name, self.subject.title
+                ))
+            })
+    }
+
+    /// Find the workflow run a `CheckSuite` notification is about
+    ///
+    /// Check suite notifications carry no URL, only a title such as "CI workflow run failed for
+    /// main branch".  The run chosen is the latest of that workflow and branch that was updated
+    /// no later than the notification.
+    pub async fn fetch_workflow_run(&self, client: &GitHubClient) -> Result<Action, Error> {
+        self.expect_subject(&[SubjectType::CheckSuite])?;
+        let (workflow, branch) = parse_check_suite_title(&self.subject.title);
+        let url = UrlBuilder::new(client.api_url(&format!(
+            "/repos/{}/actions/runs",
+            self.repository.full_name
+        )))
+        .param("branch", branch)
+        .required_param("per_page", RECENT_WORKFLOW_RUNS)
+        .build();
+        let runs = client
+            .paginate::<Runs>(&url, Some(RECENT_WORKFLOW_RUNS))
+            .await?;
+        runs.into_iter()
+            .filter(|run| run.updated_at <= self.updated_at)
+            .filter(|run| workflow.is_none_or(|w| run.name.as_deref() == Some(w)))
+            .max_by_key(|run| run.updated_at)
+            .ok_or_else(|| {
+                Error::MissingSubject(format!(
+                    "no workflow run in {} matches '{}'",
+                    self.repository.full_name, self.subject.title
+                ))
+            })
+    }
+
+    /// Fetch the open Dependabot alerts a security alert notification may be about
+    ///
+    /// These notifications do not say which alert they are about.  The alerts whose package the
+    /// title names are returned if there are any; otherwise the most recently updated are.
+    pub async fn fetch_security_alerts(
+        &self,
+        client: &GitHubClient,
+    ) -> Result<Vec<DependabotAlert>, Error> {
+        self.expect_subject(&[
+            SubjectType::RepositoryVulnerabilityAlert,
+            SubjectType::RepositoryDependabotAlertsThread,
+        ])?;
+        let url = UrlBuilder::new(client.api_url(&format!(
+            "/repos/{}/dependabot/alerts",
+            self.repository.full_name
+        )))
+        .required_param("state", "open")
+        .required_param("sort", "updated")
+        .required_param("per_page", RECENT_SECURITY_ALERTS)
+        .build();
+        let alerts = client
+            .paginate::<Vec<DependabotAlert>>(&url, Some(RECENT_SECURITY_ALERTS))
+            .await?;
+        let (named, others): (Vec<_>, Vec<_>) = alerts.into_iter().partition(|alert| {
+            self.subject
+                .title
+                .contains(&alert.security_vulnerability.package.name)
+        });
+        Ok(if named.is_empty() { others } else { named })
+    }
+
+    pub async fn fetch_issue(&self, client: &GitHubClient) -> Result<Issue, Error> {
+        self.expect_subject(&[SubjectType::Issue])?;
+        client.get_json(self.subject_url()?).await
+    }
+
+    pub async fn mark_as_read(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/notifications/threads/{}", self.id));
+
+        client
+            .execute(client.request(reqwest::Method::PATCH, &url))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark the thread done, removing it from the inbox
+    pub async fn mark_as_done(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/notifications/threads/{}", self.id));
+
+        client
+            .execute(client.request(reqwest::Method::DELETE, &url))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stop notifications for the thread until the user comments or is mentioned
+    pub async fn unsubscribe(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/notifications/threads/{}/subscription", self.id));
+
+        client
+            .execute(client.request(reqwest::Method::DELETE, &url))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mute every future notification for the thread
+    pub async fn ignore(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/notifications/threads/{}/subscription", self.id));
+
+        client
+            .execute(
+                client
+                    .request(reqwest::Method::PUT, &url)
+                    .json(&serde_json::json!({"ignored": true})),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Star the repository the notification belongs to
+    pub async fn star_repository(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/user/starred/{}", self.repository.full_name));
+
+        client
+            .execute(
+                client
+                    .request(reqwest::Method::PUT, &url)
+                    .header(reqwest::header::CONTENT_LENGTH, 0),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add `labels` to the issue or pull request the notification is about
+    pub async fn add_labels(&self, client: &GitHubClient, labels: &[String]) -> Result<(), Error> {
+        let url = self.labels_url()?;
+
+        client
+            .execute(
+                client
+                    .post(&url)
+                    .json(&serde_json::json!({ "labels": labels })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// The labels on the issue or pull request the notification is about
+    pub async fn fetch_labels(&self, client: &GitHubClient) -> Result<Vec<Label>, Error> {
+        client
+            .paginate::<Vec<Label>>(&self.labels_url()?, None)
+            .await
+    }
+
+    /// Remove `label` from the issue or pull request the notification is about
+    pub async fn remove_label(&self, client: &GitHubClient, label: &str) -> Result<(), Error> {
+        let url = format!("{}/{}", self.labels_url()?, urlencoding::encode(label));
+
+        client
+            .execute(client.request(reqwest::Method::DELETE, &url))
+            .await?;
+
+        Ok(())
+    }
+
+    fn labels_url(&self) -> Result<String, Error> {
+        self.expect_subject(&[SubjectType::Issue, SubjectType::PullRequest])?;
+        // Pull requests share their number, and their labels, with an issue.
+        Ok(format!(
+            "{}/issues/{}/labels",
+            self.repository.url,
+            self.subject_number()
+        ))
+    }
+
+    /// Subscribe to the thread again, undoing [`Notification::unsubscribe`] or
+    /// [`Notification::ignore`]
+    pub async fn resubscribe(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/notifications/threads/{}/subscription", self.id));
+
+        client
+            .execute(
+                client
+                    .request(reqwest::Method::PUT, &url)
+                    .json(&serde_json::json!({"subscribed": true, "ignored": false})),
+            )
+            .await?;
+
+        Ok(())
+    }
 
-        let client = GitHubClient::new()?;
-        let issue: Issue = client.get(&self.subject.url).send().await?.json().await?;
+    /// Whether the authenticated user has starred the notification's repository
+    pub async fn is_repository_starred(&self, client: &GitHubClient) -> Result<bool, Error> {
+        let url = client.api_url(&format!("/user/starred/{}", self.repository.full_name));
 
-        Ok(issue)
+        match client.execute(client.get(&url)).await {
+            Ok(_) => Ok(true),
+            Err(Error::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
-    pub async fn mark_as_read(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let client = GitHubClient::new()?;
-        let url = format!("https://api.github.com/notifications/threads/{}", self.id);
+    /// Unstar the repository the notification belongs to
+    pub async fn unstar_repository(&self, client: &GitHubClient) -> Result<(), Error> {
+        let url = client.api_url(&format!("/user/starred/{}", self.repository.full_name));
 
         client
-            .request(reqwest::Method::PATCH, &url)
-            .send()
-            .await?
-            .error_for_status()?;
+            .execute(client.request(reqwest::Method::DELETE, &url))
+            .await?;
 
         Ok(())
     }
 }
 
+/// How many recent workflow runs are searched for the run of a check suite notification
+const RECENT_WORKFLOW_RUNS: usize = 50;
+/// How many open Dependabot alerts are fetched for a security alert notification
+const RECENT_SECURITY_ALERTS: usize = 10;
+
+/// Split a check suite notification title, e.g. "CI workflow run failed for main branch", into
+/// the workflow ("CI") and the branch ("main")
+fn parse_check_suite_title(title: &str) -> (Option<&str>, Option<&str>) {
+    let Some((run, branch)) = title
+        .strip_suffix(" branch")
+        .and_then(|rest| rest.rsplit_once(" for "))
+    else {
+        return (None, None);
+    };
+    let workflow = run
+        .rsplit_once(" workflow run ")
+        .map(|(workflow, _)| workflow);
+    (workflow, Some(branch))
+}
+
+string_enum! {
+    /// How a file changed in a commit or pull request
+    pub enum DiffStatus {
+        Added => "added",
+        Removed => "removed",
+        Modified => "modified",
+        Renamed => "renamed",
+        Copied => "copied",
+        Changed => "changed",
+        Unchanged => "unchanged",
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct DiffEntry {
+    pub sha: Option<String>,
+    pub filename: String,
+    pub status: DiffStatus,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changes: u64,
+    pub blob_url: String,
+    pub raw_url: String,
+    pub contents_url: String,
+    /// Absent for binary files and very large diffs
+    pub patch: Option<String>,
+    pub previous_filename: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// The name, email, and time git records for an author or committer
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GitActor {
+    pub name: String,
+    pub email: String,
+    pub date: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GitTree {
+    pub sha: String,
+    pub url: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// The git commit object underneath a [`RepositoryCommit`]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GitCommit {
+    pub url: String,
+    pub author: Option<GitActor>,
+    pub committer: Option<GitActor>,
+    pub message: String,
+    pub comment_count: u64,
+    pub tree: GitTree,
+    pub verification: Option<serde_json::Value>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct CommitParent {
+    pub sha: String,
+    pub url: String,
+    pub html_url: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct CommitStats {
+    pub additions: u64,
+    pub deletions: u64,
+    pub total: u64,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// A commit as the REST API returns it, unlike the [`Commit`] of a push event
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct RepositoryCommit {
+    pub url: String,
+    pub sha: String,
+    pub node_id: String,
+    pub html_url: String,
+    pub comments_url: String,
+    pub commit: GitCommit,
+    /// The GitHub user of the git author, when GitHub can tell
+    pub author: Option<SimpleUser>,
+    pub committer: Option<SimpleUser>,
+    pub parents: Vec<CommitParent>,
+    pub stats: Option<CommitStats>,
+    pub files: Option<Vec<DiffEntry>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct CommitComment {
+    pub html_url: String,
+    pub url: String,
+    pub id: i64,
+    pub node_id: String,
+    pub body: String,
+    pub path: Option<String>,
+    pub position: Option<u64>,
+    pub line: Option<u64>,
+    pub commit_id: String,
+    pub user: Option<SimpleUser>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author_association: AuthorAssociation,
+    pub reactions: Option<ReactionRollup>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ReleaseAsset {
+    pub url: String,
+    pub browser_download_url: String,
+    pub id: i64,
+    pub node_id: String,
+    pub name: String,
+    pub label: Option<String>,
+    pub state: String,
+    pub content_type: String,
+    pub size: u64,
+    pub digest: Option<String>,
+    pub download_count: u64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub uploader: Option<SimpleUser>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct Release {
+    pub url: String,
+    pub html_url: String,
+    pub assets_url: String,
+    pub upload_url: String,
+    pub tarball_url: Option<String>,
+    pub zipball_url: Option<String>,
+    pub id: i64,
+    pub node_id: String,
+    pub tag_name: String,
+    pub target_commitish: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub body_html: Option<String>,
+    pub body_text: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub immutable: Option<bool>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub author: SimpleUser,
+    pub assets: Vec<ReleaseAsset>,
+    pub mentions_count: Option<u64>,
+    pub discussion_url: Option<String>,
+    pub reactions: Option<ReactionRollup>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// The discussions of a repository, most recently updated first, with their latest comments
+const DISCUSSIONS_QUERY: &str = "
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    discussions(first: 25, orderBy: {field: UPDATED_AT, direction: DESC}) {
+      nodes {
+        number
+        title
+        body
+        url
+        createdAt
+        updatedAt
+        closed
+        locked
+        isAnswered
+        author { login }
+        category { name }
+        labels(first: 20) { nodes { name } }
+        comments(last: 20) {
+          totalCount
+          nodes { author { login } body createdAt url }
+        }
+      }
+    }
+  }
+}";
+
+/// The `nodes` of a GraphQL connection
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Nodes<T> {
+    pub nodes: Vec<T>,
+}
+
+/// A GraphQL actor, which is `null` for deleted accounts
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GraphQlActor {
+    pub login: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// A name, as GraphQL returns discussion categories and labels
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct Named {
+    pub name: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+#[serde(rename_all = "camelCase")]
+pub struct DiscussionComment {
+    pub author: Option<GraphQlActor>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub url: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+#[serde(rename_all = "camelCase")]
+pub struct DiscussionComments {
+    pub total_count: u64,
+    /// The latest comments, oldest first
+    pub nodes: Vec<DiscussionComment>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+/// A GitHub Discussion, as fetched by GraphQL
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+#[serde(rename_all = "camelCase")]
+pub struct Discussion {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed: bool,
+    pub locked: bool,
+    /// `None` outside question-and-answer categories
+    pub is_answered: Option<bool>,
+    pub author: Option<GraphQlActor>,
+    pub category: Named,
+    pub labels: Option<Nodes<Named>>,
+    pub comments: DiscussionComments,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+impl Discussion {
+    pub fn label_names(&self) -> Vec<String> {
+        self.labels
+            .iter()
+            .flat_map(|labels| labels.nodes.iter().map(|label| label.name.clone()))
+            .collect()
+    }
+
+    /// The fetched comments made after `since`, or all of them without it
+    pub fn comments_since(&self, since: Option<DateTime<Utc>>) -> Vec<DiscussionComment> {
+        self.comments
+            .nodes
+            .iter()
+            .filter(|comment| since.is_none_or(|since| comment.created_at > since))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct DependabotPackage {
+    pub ecosystem: String,
+    pub name: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct DependabotDependency {
+    pub package: DependabotPackage,
+    pub manifest_path: String,
+    pub scope: Option<String>,
+    pub relationship: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct SecurityAdvisory {
+    pub ghsa_id: String,
+    pub cve_id: Option<String>,
+    pub summary: String,
+    pub description: String,
+    pub severity: String,
+    pub vulnerabilities: Option<serde_json::Value>,
+    pub cvss: Option<serde_json::Value>,
+    pub cvss_severities: Option<serde_json::Value>,
+    pub epss: Option<serde_json::Value>,
+    pub cwes: Option<serde_json::Value>,
+    pub identifiers: Option<serde_json::Value>,
+    pub references: Option<serde_json::Value>,
+    pub published_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub withdrawn_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct PatchedVersion {
+    pub identifier: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct SecurityVulnerability {
+    pub package: DependabotPackage,
+    pub severity: String,
+    pub vulnerable_version_range: String,
+    pub first_patched_version: Option<PatchedVersion>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct DependabotAlert {
+    pub number: u64,
+    pub state: String,
+    pub dependency: DependabotDependency,
+    pub security_advisory: SecurityAdvisory,
+    pub security_vulnerability: SecurityVulnerability,
+    pub url: String,
+    pub html_url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub dismissed_at: Option<DateTime<Utc>>,
+    pub dismissed_by: Option<SimpleUser>,
+    pub dismissed_reason: Option<String>,
+    pub dismissed_comment: Option<String>,
+    pub fixed_at: Option<DateTime<Utc>>,
+    pub auto_dismissed_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct ReferencedWorkflow {
     path: String,
     sha: String,
     r#ref: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Action {
     pub id: i64,
     pub name: Option<String>,
@@ -841,17 +1788,17 @@ pub struct Action {
     pub run_attempt: Option<u64>,
     pub referenced_workflows: Vec<ReferencedWorkflow>,
     pub event: String,
-    pub status: Option<String>,
-    pub conclusion: Option<String>,
+    pub status: Option<RunStatus>,
+    pub conclusion: Option<RunConclusion>,
     pub workflow_id: u64,
     pub url: String,
     pub html_url: String,
     pub pull_requests: Vec<IssuePullRequest>,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub actor: Option<SimpleUser>,
     pub triggering_actor: Option<SimpleUser>,
-    pub run_started_at: Option<String>,
+    pub run_started_at: Option<DateTime<Utc>>,
     pub jobs_url: String,
     pub logs_url: String,
     pub check_suite_url: String,
@@ -865,25 +1812,51 @@ pub struct Action {
     pub head_repository: Repository,
     pub head_repository_id: Option<u64>,
     pub display_title: String,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Runs {
     pub total_count: u64,
     pub workflow_runs: Vec<Action>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct RunJobs {
     pub total_count: u64,
     pub jobs: Vec<Job>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+impl Page for Runs {
+    type Item = Action;
+
+    fn into_items(self) -> Vec<Action> {
+        self.workflow_runs
+    }
+}
+
+impl Page for RunJobs {
+    type Item = Job;
+
+    fn into_items(self) -> Vec<Job> {
+        self.jobs
+    }
 }
 
 impl Action {
     #[allow(clippy::too_many_arguments)]
     pub async fn fetch_all(
+        client: &GitHubClient,
         owner: String,
         repo: String,
         actor: Option<String>,
@@ -892,46 +1865,44 @@ impl Action {
         workflow_run_status: Option<String>,
         per_page: Option<u64>,
         page: Option<u64>,
-    ) -> Result<Vec<Action>, Box<dyn std::error::Error>> {
-        let url = UrlBuilder::new(format!(
-            "https://api.github.com/repos/{owner}/{repo}/actions/runs"
-        ))
-        .param("actor", actor)
-        .param("workflow_run_branch", workflow_run_branch)
-        .param("event", event)
-        .param("workflow_run_status", workflow_run_status)
-        .param("per_page", per_page)
-        .param("page", page)
-        .build();
+        limit: Option<usize>,
+    ) -> Result<Vec<Action>, Error> {
+        let url = UrlBuilder::new(client.api_url(&format!("/repos/{owner}/{repo}/actions/runs")))
+            .param("actor", actor)
+            .param("workflow_run_branch", workflow_run_branch)
+            .param("event", event)
+            .param("workflow_run_status", workflow_run_status)
+            .param("per_page", per_page)
+            .param("page", page)
+            .build();
 
-        let client = GitHubClient::new()?;
-        let mut runs: Runs = client.get(&url).send().await?.json().await?;
+        let mut runs = client.paginate::<Runs>(&url, limit).await?;
 
-        runs.workflow_runs.sort_by_key(|n| n.updated_at.clone());
-        Ok(runs.workflow_runs)
+        runs.sort_by_key(|n| n.updated_at);
+        Ok(runs)
     }
 
-    pub async fn fetch_jobs(&self) -> Result<Vec<Job>, Box<dyn std::error::Error>> {
-        let client = GitHubClient::new()?;
-        let run_jobs: RunJobs = client.get(&self.jobs_url).send().await?.json().await?;
-
-        Ok(run_jobs.jobs)
+    pub async fn fetch_jobs(&self, client: &GitHubClient) -> Result<Vec<Job>, Error> {
+        client.paginate::<RunJobs>(&self.jobs_url, None).await
     }
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct JobStep {
-    pub status: String,
-    pub conclusion: Option<String>,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
     pub name: String,
     pub number: u64,
-    pub started_at: Option<String>,
-    pub completed_at: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Job {
     pub id: i64,
     pub run_id: u64,
@@ -941,11 +1912,11 @@ pub struct Job {
     pub head_sha: String,
     pub url: String,
     pub html_url: Option<String>,
-    pub status: String,
-    pub conclusion: Option<String>,
-    pub created_at: String,
-    pub started_at: String,
-    pub completed_at: Option<String>,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
     pub name: String,
     pub steps: Vec<JobStep>,
     pub check_run_url: String,
@@ -956,4 +1927,130 @@ pub struct Job {
     pub runner_group_name: Option<String>,
     pub workflow_name: Option<String>,
     pub head_branch: Option<String>,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: crate::lenient::Extra,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_round_trip() {
+        let value = serde_json::json!({
+            "status": "completed",
+            "conclusion": "success",
+            "name": "Run cargo test",
+            "number": 4,
+            "started_at": "2024-01-02T03:04:05Z",
+            "completed_at": null,
+        });
+        let step: JobStep = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(
+            step.started_at,
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap())
+        );
+        assert_eq!(serde_json::to_value(&step).unwrap(), value);
+    }
+
+    #[test]
+    fn string_enums_decode_known_values() {
+        let step: JobStep = serde_json::from_value(serde_json::json!({
+            "status": "completed",
+            "conclusion": "timed_out",
+            "name": "Build",
+            "number": 1,
+            "started_at": null,
+            "completed_at": null,
+        }))
+        .unwrap();
+        assert_eq!(step.status, RunStatus::Completed);
+        assert_eq!(step.conclusion, Some(RunConclusion::TimedOut));
+        assert_eq!(
+            NotificationReason::from("ci_activity"),
+            NotificationReason::CiActivity
+        );
+        assert_eq!(SubjectType::PullRequest.to_string(), "PullRequest");
+    }
+
+    #[test]
+    fn string_enums_keep_unknown_values() {
+        let reason: NotificationReason =
+            serde_json::from_value(serde_json::json!("brand_new_reason")).unwrap();
+        assert_eq!(
+            reason,
+            NotificationReason::Unknown("brand_new_reason".to_string())
+        );
+        assert_eq!(
+            serde_json::to_value(&reason).unwrap(),
+            serde_json::json!("brand_new_reason")
+        );
+    }
+
+    #[test]
+    fn check_suite_titles_name_workflow_and_branch() {
+        assert_eq!(
+            (Some("CI"), Some("main")),
+            parse_check_suite_title("CI workflow run failed for main branch")
+        );
+        assert_eq!(
+            (Some("Build and test"), Some("feature/for real")),
+            parse_check_suite_title(
+                "Build and test workflow run succeeded for feature/for real branch"
+            )
+        );
+        assert_eq!((None, None), parse_check_suite_title("Something else"));
+    }
+
+    #[test]
+    fn discussions_decode_from_graphql() {
+        let discussion: Discussion = serde_json::from_value(serde_json::json!({
+            "number": 12,
+            "title": "Roadmap",
+            "body": "What next?",
+            "url": "https://github.com/rescrv/ghai/discussions/12",
+            "createdAt": "2024-01-01T00:00:00Z",
+            "updatedAt": "2024-01-03T00:00:00Z",
+            "closed": false,
+            "locked": false,
+            "isAnswered": null,
+            "author": null,
+            "category": {"name": "Ideas"},
+            "labels": {"nodes": [{"name": "planning"}]},
+            "comments": {
+                "totalCount": 2,
+                "nodes": [
+                    {"author": {"login": "alice"}, "body": "Old", "createdAt": "2024-01-01T12:00:00Z",
+                     "url": "https://github.com/rescrv/ghai/discussions/12#c1"},
+                    {"author": {"login": "bob"}, "body": "New", "createdAt": "2024-01-02T12:00:00Z",
+                     "url": "https://github.com/rescrv/ghai/discussions/12#c2"},
+                ],
+            },
+        }))
+        .unwrap();
+        assert_eq!(vec!["planning".to_string()], discussion.label_names());
+        let since = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let comments = discussion.comments_since(Some(since));
+        assert_eq!(1, comments.len());
+        assert_eq!("New", comments[0].body);
+        assert_eq!(2, discussion.comments_since(None).len());
+    }
+
+    #[test]
+    fn timestamps_normalize_offsets() {
+        let step: JobStep = serde_json::from_value(serde_json::json!({
+            "status": "in_progress",
+            "conclusion": null,
+            "name": "Build",
+            "number": 1,
+            "started_at": "2024-01-01T19:04:05-08:00",
+            "completed_at": null,
+        }))
+        .unwrap();
+        assert_eq!(
+            step.started_at,
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap())
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
 /// XML builder utility for constructing XML documents
 pub struct XmlBuilder {
     content: String,
@@ -13,12 +15,33 @@ impl XmlBuilder {
         }
     }
 
+    /// Create a builder for content nested `indent_level` sections deep
+    fn indented(indent_level: usize) -> Self {
+        Self {
+            content: String::new(),
+            indent_level,
+        }
+    }
+
     /// Add a section with nested content
-    pub fn section<F>(mut self, name: &str, builder: F) -> Self
+    pub fn section<F>(self, name: &str, builder: F) -> Self
     where
         F: FnOnce(Self) -> Self,
     {
-        self.add_line(&format!("<{}>", name));
+        self.section_with_attributes(name, &[], builder)
+    }
+
+    /// Add a section with attributes and nested content; attribute values are escaped
+    pub fn section_with_attributes<F>(
+        mut self,
+        name: &str,
+        attributes: &[(&str, String)],
+        builder: F,
+    ) -> Self
+    where
+        F: FnOnce(Self) -> Self,
+    {
+        self.add_line(&format!("<{}{}>", name, format_attributes(attributes)));
         self.indent_level += 1;
         self = builder(self);
         self.indent_level -= 1;
@@ -26,6 +49,12 @@ impl XmlBuilder {
         self
     }
 
+    /// Add an element with attributes and no content; attribute values are escaped
+    pub fn empty_element(mut self, name: &str, attributes: &[(&str, String)]) -> Self {
+        self.add_line(&format!("<{}{} />", name, format_attributes(attributes)));
+        self
+    }
+
     /// Add a field with a value
     pub fn field(mut self, name: &str, value: impl std::fmt::Display) -> Self {
         self.add_line(&format!("<{}>{}</{}>", name, value, name));
@@ -48,6 +77,20 @@ impl XmlBuilder {
         self
     }
 
+    /// Add a field with text escaped and cut to at most `max_chars` characters, marked
+    /// `truncated="true"` if it was cut
+    pub fn text_field(self, name: &str, text: &str, max_chars: usize) -> Self {
+        if text.chars().count() <= max_chars {
+            return self.field(name, escape_xml(text));
+        }
+        let excerpt: String = text.chars().take(max_chars).collect();
+        self.raw_line(&format!(
+            "<{0} truncated=\"true\">{1}</{0}>",
+            name,
+            escape_xml(&excerpt)
+        ))
+    }
+
     /// Build the final XML string
     pub fn build(self) -> String {
         self.content
@@ -161,44 +204,174 @@ pub fn build_issue_statistics_context(comments: u64) -> String {
         .build()
 }
 
+/// How many characters of a comment or review body a context shows
+const MAX_COMMENT_CHARS: usize = 500;
+
 /// Build comments since last read XML section
 pub fn build_comments_since_last_read_context(comments: &[crate::IssueComment]) -> String {
-    if comments.is_empty() {
-        return String::from("<comments_since_last_read></comments_since_last_read>\n");
-    }
+    render_list(
+        "comments_since_last_read",
+        &issue_comment_items(comments),
+        true,
+        usize::MAX,
+    )
+}
 
-    XmlBuilder::new()
-        .section("comments_since_last_read", |mut b| {
-            for comment in comments {
-                b = b.section("comment", |cb| {
+fn issue_comment_items(comments: &[crate::IssueComment]) -> Vec<String> {
+    comments
+        .iter()
+        .map(|comment| {
+            XmlBuilder::indented(1)
+                .section("comment", |cb| {
                     let mut cb = cb
                         .field("id", comment.id)
-                        .field("created_at", &comment.created_at)
-                        .field("updated_at", &comment.updated_at);
+                        .field("created_at", timestamp(&comment.created_at))
+                        .field("updated_at", timestamp(&comment.updated_at));
 
                     if let Some(user) = &comment.user {
                         cb = cb.field("author", &user.login);
                     }
 
                     if let Some(body) = &comment.body {
-                        // Truncate long comments to keep context manageable
-                        if body.len() > 500 {
-                            let truncated: String = body.chars().take(500).collect();
-                            cb = cb.field(
-                                "body",
-                                escape_xml(&format!("{}... (truncated)", truncated)),
-                            );
-                        } else {
-                            cb = cb.field("body", escape_xml(body));
-                        }
+                        cb = cb.text_field("body", body, MAX_COMMENT_CHARS);
+                    }
+
+                    cb
+                })
+                .build()
+        })
+        .collect()
+}
+
+/// Build reviews XML section
+pub fn build_reviews_context(reviews: &[crate::PullRequestReview]) -> String {
+    render_list("reviews", &review_items(reviews), true, usize::MAX)
+}
+
+fn review_items(reviews: &[crate::PullRequestReview]) -> Vec<String> {
+    reviews
+        .iter()
+        .map(|review| {
+            XmlBuilder::indented(1)
+                .section("review", |rb| {
+                    let rb = rb
+                        .field("id", review.id)
+                        .field("state", &review.state)
+                        .optional_field("submitted_at", review.submitted_at.as_ref().map(timestamp))
+                        .optional_field("author", review.user.as_ref().map(|u| &u.login));
+                    match review.body.as_deref().filter(|body| !body.is_empty()) {
+                        Some(body) => rb.text_field("body", body, MAX_COMMENT_CHARS),
+                        None => rb,
                     }
+                })
+                .build()
+        })
+        .collect()
+}
+
+/// Build review comments XML section
+pub fn build_review_comments_context(comments: &[crate::PullRequestReviewComment]) -> String {
+    render_list(
+        "review_comments",
+        &review_comment_items(comments),
+        true,
+        usize::MAX,
+    )
+}
 
+fn review_comment_items(comments: &[crate::PullRequestReviewComment]) -> Vec<String> {
+    comments
+        .iter()
+        .map(|comment| {
+            XmlBuilder::indented(1)
+                .section("comment", |cb| {
+                    let mut cb = cb
+                        .field("id", comment.id)
+                        .field("created_at", timestamp(&comment.created_at))
+                        .optional_field("author", comment.user.as_ref().map(|u| &u.login))
+                        .optional_field("path", comment.path.as_deref().map(escape_xml))
+                        .optional_field("line", comment.line.or(comment.original_line));
+                    if let Some(reply_to) =
+                        comment.in_reply_to_id.as_ref().and_then(|id| id.as_u64())
+                    {
+                        cb = cb.field("in_reply_to", reply_to);
+                    }
+                    if let Some(body) = &comment.body {
+                        cb = cb.text_field("body", body, MAX_COMMENT_CHARS);
+                    }
                     cb
-                });
+                })
+                .build()
+        })
+        .collect()
+}
+
+/// Build changed files XML section, with patch excerpts of at most `patch_budget` characters in all
+///
+/// The budget is shared evenly among the files that have a patch, in order; a file whose patch
+/// needs less than its share leaves the rest to the files after it.  Excerpts end on a whole line
+/// where possible.
+pub fn build_changed_files_context(files: &[crate::DiffEntry], patch_budget: usize) -> String {
+    render_list(
+        "changed_files",
+        &changed_file_items(files, patch_budget),
+        false,
+        usize::MAX,
+    )
+}
+
+fn changed_file_items(files: &[crate::DiffEntry], patch_budget: usize) -> Vec<String> {
+    let mut remaining = patch_budget;
+    let mut patches_left = files.iter().filter(|file| file.patch.is_some()).count();
+    files
+        .iter()
+        .map(|file| {
+            let mut attributes = vec![
+                ("path", file.filename.clone()),
+                ("status", file.status.to_string()),
+                ("additions", file.additions.to_string()),
+                ("deletions", file.deletions.to_string()),
+            ];
+            if let Some(previous) = &file.previous_filename {
+                attributes.push(("previous_path", previous.clone()));
             }
-            b
+            let excerpt = file.patch.as_deref().and_then(|patch| {
+                let share = remaining / patches_left;
+                patches_left -= 1;
+                let (excerpt, truncated) = excerpt_patch(patch, share);
+                remaining -= excerpt.chars().count();
+                (!excerpt.is_empty()).then_some((excerpt, truncated))
+            });
+            let b = XmlBuilder::indented(1);
+            match excerpt {
+                Some((excerpt, truncated)) => {
+                    b.section_with_attributes("file", &attributes, |fb| {
+                        let open = if truncated {
+                            "<patch truncated=\"true\">"
+                        } else {
+                            "<patch>"
+                        };
+                        fb.raw_line(&format!("{}\n{}\n</patch>", open, escape_xml(&excerpt)))
+                    })
+                }
+                None => b.empty_element("file", &attributes),
+            }
+            .build()
         })
-        .build()
+        .collect()
+}
+
+/// The first `max_chars` characters of `patch`, cut back to a line boundary, and whether any of
+/// the patch was left out
+fn excerpt_patch(patch: &str, max_chars: usize) -> (String, bool) {
+    if patch.chars().count() <= max_chars {
+        return (patch.to_string(), false);
+    }
+    let mut excerpt: String = patch.chars().take(max_chars).collect();
+    if let Some(newline) = excerpt.rfind('\n') {
+        excerpt.truncate(newline);
+    }
+    (excerpt, true)
 }
 
 /// Build dates XML section
@@ -273,20 +446,9 @@ pub fn build_branches_context(
         .build()
 }
 
-/// Build description XML section with proper escaping and truncation
-pub fn build_description_context(body: Option<&str>) -> String {
-    match body {
-        Some(body) if body.len() > 500 => {
-            format!(
-                "<description truncated=\"true\">\n{}\n... (truncated) ...\n</description>\n",
-                escape_xml(&body[..500])
-            )
-        }
-        Some(body) => {
-            format!("<description>\n{}\n</description>\n", escape_xml(body))
-        }
-        None => "<description>\n</description>\n".to_string(),
-    }
+/// Build description XML section, escaped and cut to at most `max_chars` characters
+pub fn build_description_context(body: Option<&str>, max_chars: usize) -> String {
+    render_text("description", body, max_chars)
 }
 
 /// Build pull request context XML section
@@ -338,39 +500,25 @@ pub fn build_issue_context(number: u64, title: &str, state: &str) -> String {
         .build()
 }
 
-/// Build complete Pull Request notification context XML
+/// Build complete Pull Request notification context XML in about `max_tokens` tokens
+#[allow(clippy::too_many_arguments)]
 pub fn build_pull_request_notification_context(
     thread: &crate::Notification,
     pr: &crate::PullRequest,
     comments_since_last_read: &[crate::IssueComment],
+    reviews_since_last_read: &[crate::PullRequestReview],
+    review_comments_since_last_read: &[crate::PullRequestReviewComment],
+    changed_files: Option<&[crate::DiffEntry]>,
+    patch_budget: usize,
+    max_tokens: usize,
 ) -> String {
-    let mut context = String::new();
-
-    // <notification_context>
-    context.push_str(&build_notification_context(
-        &thread.id,
-        &thread.reason,
-        thread.unread,
-        &thread.updated_at,
-        thread.last_read_at.as_deref(),
-    ));
-    context.push('\n');
-
-    // <repository_context>
-    context.push_str(&build_repository_context(
-        &thread.repository.full_name,
-        &thread.repository.owner.login,
-        &thread.repository.owner.r#type,
-        thread.repository.private,
-        thread.repository.description.as_deref(),
-    ));
-    context.push('\n');
+    let mut header = build_thread_context(thread);
 
     // <pull_request>
-    context.push_str(&build_pull_request_context(
+    header.push_str(&build_pull_request_context(
         pr.number,
         &pr.title,
-        &pr.state,
+        pr.state.as_str(),
         pr.draft,
         pr.merged,
         pr.mergeable,
@@ -378,30 +526,30 @@ pub fn build_pull_request_notification_context(
     ));
 
     // <author>
-    context.push_str(&build_author_context(
+    header.push_str(&build_author_context(
         &pr.user.login,
         pr.user.name.as_deref(),
         &format!("{:?}", pr.author_association),
     ));
 
     // <dates>
-    context.push_str(&build_dates_context(
-        &pr.created_at,
-        &pr.updated_at,
-        pr.closed_at.as_deref(),
-        pr.merged_at.as_deref(),
+    header.push_str(&build_dates_context(
+        &timestamp(&pr.created_at),
+        &timestamp(&pr.updated_at),
+        pr.closed_at.as_ref().map(timestamp).as_deref(),
+        pr.merged_at.as_ref().map(timestamp).as_deref(),
     ));
 
     // <labels>
     let label_names: Vec<String> = pr.labels.iter().map(|l| l.name().to_string()).collect();
-    context.push_str(&build_labels_context(&label_names));
+    header.push_str(&build_labels_context(&label_names));
 
     // <assignees>
     let assignee_names: Vec<String> = pr
         .assignees
         .as_ref()
         .map_or(Vec::new(), |a| a.iter().map(|u| u.login.clone()).collect());
-    context.push_str(&build_assignees_context(
+    header.push_str(&build_assignees_context(
         pr.assignee.as_ref().map(|a| a.login.as_str()),
         &assignee_names,
     ));
@@ -411,10 +559,10 @@ pub fn build_pull_request_notification_context(
         .requested_reviewers
         .as_ref()
         .map_or(Vec::new(), |r| r.iter().map(|u| u.login.clone()).collect());
-    context.push_str(&build_reviewers_context(&reviewer_names));
+    header.push_str(&build_reviewers_context(&reviewer_names));
 
     // <statistics>
-    context.push_str(&build_statistics_context(
+    header.push_str(&build_statistics_context(
         pr.comments,
         pr.review_comments,
         pr.commits,
@@ -424,63 +572,54 @@ pub fn build_pull_request_notification_context(
     ));
 
     // <branches>
-    context.push_str(&build_branches_context(
+    header.push_str(&build_branches_context(
         &pr.head.r#ref,
         &pr.head.sha,
         &pr.base.r#ref,
         &pr.base.sha,
     ));
 
-    // <description>
-    context.push_str(&build_description_context(pr.body.as_deref()));
-
-    // <comments_since_last_read>
-    context.push_str(&build_comments_since_last_read_context(
-        comments_since_last_read,
-    ));
-
-    context.push_str("</pull_request>\n\n");
+    // New reviews and comments come first, then the description, then the files.
+    let mut context = Context::new(max_tokens).fixed(header);
+    if let Some(files) = changed_files {
+        context = context.leading(4, "changed_files", changed_file_items(files, patch_budget));
+    }
     context
+        .text(3, "description", pr.body.as_deref())
+        .latest(
+            1,
+            "comments_since_last_read",
+            issue_comment_items(comments_since_last_read),
+        )
+        .latest(0, "reviews", review_items(reviews_since_last_read))
+        .latest(
+            2,
+            "review_comments",
+            review_comment_items(review_comments_since_last_read),
+        )
+        .fixed("</pull_request>\n\n")
+        .build()
 }
 
-/// Build complete Issue notification context XML
+/// Build complete Issue notification context XML in about `max_tokens` tokens
 pub fn build_issue_notification_context(
     thread: &crate::Notification,
     issue: &crate::Issue,
     comments_since_last_read: &[crate::IssueComment],
+    max_tokens: usize,
 ) -> String {
-    let mut context = String::new();
-
-    // <notification_context>
-    context.push_str(&build_notification_context(
-        &thread.id,
-        &thread.reason,
-        thread.unread,
-        &thread.updated_at,
-        thread.last_read_at.as_deref(),
-    ));
-    context.push('\n');
-
-    // <repository_context>
-    context.push_str(&build_repository_context(
-        &thread.repository.full_name,
-        &thread.repository.owner.login,
-        &thread.repository.owner.r#type,
-        thread.repository.private,
-        thread.repository.description.as_deref(),
-    ));
-    context.push('\n');
+    let mut header = build_thread_context(thread);
 
     // <issue>
-    context.push_str(&build_issue_context(
+    header.push_str(&build_issue_context(
         issue.number,
         &issue.title,
-        &issue.state,
+        issue.state.as_str(),
     ));
 
     // <author>
     if let Some(user) = &issue.user {
-        context.push_str(&build_author_context(
+        header.push_str(&build_author_context(
             &user.login,
             user.name.as_deref(),
             &format!("{:?}", issue.author_association),
@@ -488,42 +627,566 @@ pub fn build_issue_notification_context(
     }
 
     // <dates>
-    context.push_str(&build_dates_context(
-        &issue.created_at,
-        &issue.updated_at,
-        issue.closed_at.as_deref(),
+    header.push_str(&build_dates_context(
+        &timestamp(&issue.created_at),
+        &timestamp(&issue.updated_at),
+        issue.closed_at.as_ref().map(timestamp).as_deref(),
         None, // issues don't have merged_at
     ));
 
     // <labels>
     let label_names: Vec<String> = issue.labels.iter().map(|l| l.name().to_string()).collect();
-    context.push_str(&build_labels_context(&label_names));
+    header.push_str(&build_labels_context(&label_names));
 
     // <assignees>
     let assignee_names: Vec<String> = issue
         .assignees
         .as_ref()
         .map_or(Vec::new(), |a| a.iter().map(|u| u.login.clone()).collect());
-    context.push_str(&build_assignees_context(
+    header.push_str(&build_assignees_context(
         issue.assignee.as_ref().map(|a| a.login.as_str()),
         &assignee_names,
     ));
 
     // <statistics>
-    context.push_str(&build_issue_statistics_context(issue.comments));
+    header.push_str(&build_issue_statistics_context(issue.comments));
+
+    Context::new(max_tokens)
+        .fixed(header)
+        .text(1, "description", issue.body.as_deref())
+        .latest(
+            0,
+            "comments_since_last_read",
+            issue_comment_items(comments_since_last_read),
+        )
+        .fixed("</issue>\n\n")
+        .build()
+}
 
-    // <description>
-    context.push_str(&build_description_context(issue.body.as_deref()));
+/// Build the notification and repository sections every notification context starts with
+fn build_thread_context(thread: &crate::Notification) -> String {
+    let mut context = String::new();
 
-    // <comments_since_last_read>
-    context.push_str(&build_comments_since_last_read_context(
-        comments_since_last_read,
+    // <notification_context>
+    context.push_str(&build_notification_context(
+        &thread.id,
+        thread.reason.as_str(),
+        thread.unread,
+        &timestamp(&thread.updated_at),
+        thread.last_read_at.as_ref().map(timestamp).as_deref(),
     ));
+    context.push('\n');
 
-    context.push_str("</issue>\n\n");
+    // <repository_context>
+    context.push_str(&build_repository_context(
+        &thread.repository.full_name,
+        &thread.repository.owner.login,
+        &thread.repository.owner.r#type,
+        thread.repository.private,
+        thread.repository.description.as_deref(),
+    ));
+    context.push('\n');
     context
 }
 
+/// Build complete Commit notification context XML in about `max_tokens` tokens
+pub fn build_commit_notification_context(
+    thread: &crate::Notification,
+    commit: &crate::RepositoryCommit,
+    comments_since_last_read: &[crate::CommitComment],
+    max_tokens: usize,
+) -> String {
+    let mut header = build_thread_context(thread);
+    let git = &commit.commit;
+    let author = commit
+        .author
+        .as_ref()
+        .map(|user| user.login.clone())
+        .or_else(|| git.author.as_ref().map(|actor| actor.name.clone()));
+    let committed_at = git
+        .committer
+        .as_ref()
+        .and_then(|actor| actor.date.as_ref())
+        .map(timestamp);
+
+    // <commit>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("commit", |b| {
+                b.field("sha", &commit.sha)
+                    .optional_field("author", author.map(|a| escape_xml(&a)))
+                    .optional_field("committed_at", committed_at)
+            })
+            .build(),
+    );
+
+    // <statistics>
+    if let Some(stats) = &commit.stats {
+        header.push_str(
+            &XmlBuilder::new()
+                .section("statistics", |b| {
+                    b.field("additions", stats.additions)
+                        .field("deletions", stats.deletions)
+                        .field("comments", git.comment_count)
+                })
+                .build(),
+        );
+    }
+
+    let comments = comments_since_last_read
+        .iter()
+        .map(|comment| {
+            XmlBuilder::indented(1)
+                .section("comment", |cb| {
+                    cb.field("id", comment.id)
+                        .field("created_at", timestamp(&comment.created_at))
+                        .optional_field("author", comment.user.as_ref().map(|u| &u.login))
+                        .optional_field("path", comment.path.as_deref().map(escape_xml))
+                        .text_field("body", &comment.body, MAX_COMMENT_CHARS)
+                })
+                .build()
+        })
+        .collect();
+    let files = commit.files.as_deref().unwrap_or_default();
+    Context::new(max_tokens)
+        .fixed(header)
+        .text(1, "message", Some(&git.message))
+        .leading(2, "changed_files", changed_file_items(files, 0))
+        .latest(0, "comments_since_last_read", comments)
+        .fixed("\n")
+        .build()
+}
+
+/// Build complete Release notification context XML in about `max_tokens` tokens
+pub fn build_release_notification_context(
+    thread: &crate::Notification,
+    release: &crate::Release,
+    max_tokens: usize,
+) -> String {
+    let mut header = build_thread_context(thread);
+
+    // <release>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("release", |b| {
+                b.field("tag", escape_xml(&release.tag_name))
+                    .optional_field("name", release.name.as_deref().map(escape_xml))
+                    .field("target", escape_xml(&release.target_commitish))
+                    .field("draft", release.draft)
+                    .field("prerelease", release.prerelease)
+                    .field("assets", release.assets.len())
+            })
+            .build(),
+    );
+
+    // <author>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("author", |b| {
+                b.field("login", &release.author.login)
+                    .optional_field("name", release.author.name.as_deref().map(escape_xml))
+            })
+            .build(),
+    );
+
+    // <dates>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("dates", |b| {
+                b.field("created_at", timestamp(&release.created_at))
+                    .optional_field("published_at", release.published_at.as_ref().map(timestamp))
+            })
+            .build(),
+    );
+
+    Context::new(max_tokens)
+        .fixed(header)
+        .text(0, "description", release.body.as_deref())
+        .fixed("\n")
+        .build()
+}
+
+/// Build complete Discussion notification context XML in about `max_tokens` tokens
+pub fn build_discussion_notification_context(
+    thread: &crate::Notification,
+    discussion: &crate::Discussion,
+    max_tokens: usize,
+) -> String {
+    let mut header = build_thread_context(thread);
+
+    // <discussion>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("discussion", |b| {
+                b.field("number", discussion.number)
+                    .field("title", escape_xml(&discussion.title))
+                    .field("category", escape_xml(&discussion.category.name))
+                    .field("state", if discussion.closed { "closed" } else { "open" })
+                    .field("locked", discussion.locked)
+                    .optional_field("answered", discussion.is_answered)
+                    .optional_field("author", discussion.author.as_ref().map(|a| &a.login))
+            })
+            .build(),
+    );
+
+    // <dates>
+    header.push_str(&build_dates_context(
+        &timestamp(&discussion.created_at),
+        &timestamp(&discussion.updated_at),
+        None,
+        None,
+    ));
+
+    // <labels>
+    header.push_str(&build_labels_context(&discussion.label_names()));
+
+    // <statistics>
+    header.push_str(&build_issue_statistics_context(
+        discussion.comments.total_count,
+    ));
+
+    let comments = discussion
+        .comments_since(thread.last_read_at)
+        .iter()
+        .map(|comment| {
+            XmlBuilder::indented(1)
+                .section("comment", |cb| {
+                    cb.field("created_at", timestamp(&comment.created_at))
+                        .optional_field("author", comment.author.as_ref().map(|a| &a.login))
+                        .text_field("body", &comment.body, MAX_COMMENT_CHARS)
+                })
+                .build()
+        })
+        .collect();
+    Context::new(max_tokens)
+        .fixed(header)
+        .text(1, "description", Some(&discussion.body))
+        .latest(0, "comments_since_last_read", comments)
+        .fixed("\n")
+        .build()
+}
+
+/// Build complete CheckSuite notification context XML, from the workflow run it reports on, in
+/// about `max_tokens` tokens
+pub fn build_workflow_run_notification_context(
+    thread: &crate::Notification,
+    run: &crate::Action,
+    jobs: &[crate::Job],
+    max_tokens: usize,
+) -> String {
+    let mut header = build_thread_context(thread);
+
+    // <workflow_run>
+    header.push_str(
+        &XmlBuilder::new()
+            .section("workflow_run", |b| {
+                b.optional_field("workflow", run.name.as_deref().map(escape_xml))
+                    .field("title", escape_xml(&run.display_title))
+                    .field("run_number", run.run_number)
+                    .field("event", &run.event)
+                    .optional_field("status", run.status.as_ref())
+                    .optional_field("conclusion", run.conclusion.as_ref())
+                    .optional_field("branch", run.head_branch.as_deref().map(escape_xml))
+                    .field("head_sha", &run.head_sha[..8.min(run.head_sha.len())])
+                    .optional_field("actor", run.actor.as_ref().map(|a| &a.login))
+                    .field("created_at", timestamp(&run.created_at))
+            })
+            .build(),
+    );
+
+    let jobs = jobs
+        .iter()
+        .map(|job| {
+            let conclusion = job
+                .conclusion
+                .as_ref()
+                .map_or(job.status.to_string(), ToString::to_string);
+            let mut attributes = vec![("conclusion", conclusion)];
+            let failed: Vec<&str> = job
+                .steps
+                .iter()
+                .filter(|step| step.conclusion == Some(crate::RunConclusion::Failure))
+                .map(|step| step.name.as_str())
+                .collect();
+            if !failed.is_empty() {
+                attributes.push(("failed_steps", failed.join(", ")));
+            }
+            XmlBuilder::indented(1)
+                .raw_line(&format!(
+                    "<job{}>{}</job>",
+                    format_attributes(&attributes),
+                    escape_xml(&job.name)
+                ))
+                .build()
+        })
+        .collect();
+    Context::new(max_tokens)
+        .fixed(header)
+        .leading(0, "jobs", jobs)
+        .fixed("\n")
+        .build()
+}
+
+/// Build complete security alert notification context XML in about `max_tokens` tokens
+pub fn build_security_alert_notification_context(
+    thread: &crate::Notification,
+    alerts: &[crate::DependabotAlert],
+    max_tokens: usize,
+) -> String {
+    let alerts = alerts
+        .iter()
+        .map(|alert| {
+            let advisory = &alert.security_advisory;
+            let vulnerability = &alert.security_vulnerability;
+            XmlBuilder::indented(1)
+                .section("alert", |ab| {
+                    ab.field("number", alert.number)
+                        .field("state", &alert.state)
+                        .field("severity", &vulnerability.severity)
+                        .field("package", escape_xml(&vulnerability.package.name))
+                        .field("ecosystem", &vulnerability.package.ecosystem)
+                        .field("manifest", escape_xml(&alert.dependency.manifest_path))
+                        .field(
+                            "vulnerable_versions",
+                            escape_xml(&vulnerability.vulnerable_version_range),
+                        )
+                        .optional_field(
+                            "patched_version",
+                            vulnerability
+                                .first_patched_version
+                                .as_ref()
+                                .map(|v| escape_xml(&v.identifier)),
+                        )
+                        .field("advisory", &advisory.ghsa_id)
+                        .optional_field("cve", advisory.cve_id.as_ref())
+                        .field("summary", escape_xml(&advisory.summary))
+                })
+                .build()
+        })
+        .collect();
+    Context::new(max_tokens)
+        .fixed(build_thread_context(thread))
+        .leading(0, "security_alerts", alerts)
+        .fixed("\n")
+        .build()
+}
+
+////////////////////////////////////////////// Context /////////////////////////////////////////////
+
+/// Roughly how many characters of context make up one token
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// How many tokens a notification context may take unless told otherwise
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8000;
+
+/// A notification context assembled against an overall budget of tokens
+///
+/// Sections are added in document order.  Fixed sections are always kept whole.  Elastic sections
+/// share what the fixed ones leave, in order of priority (lowest first): each takes what it needs
+/// of the remaining budget, or shrinks to fit it.  Text loses its end, lists lose items, and a
+/// section that lost anything says so with `truncated="true"`.
+pub struct Context {
+    max_chars: usize,
+    sections: Vec<Section>,
+}
+
+enum Section {
+    Fixed(String),
+    Text {
+        priority: u8,
+        name: &'static str,
+        text: Option<String>,
+    },
+    List {
+        priority: u8,
+        name: &'static str,
+        items: Vec<String>,
+        keep_latest: bool,
+    },
+}
+
+impl Context {
+    /// Start a context of about `max_tokens` tokens
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_chars: max_tokens.saturating_mul(CHARS_PER_TOKEN),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Add XML that is always kept whole
+    pub fn fixed(mut self, xml: impl Into<String>) -> Self {
+        self.sections.push(Section::Fixed(xml.into()));
+        self
+    }
+
+    /// Add a text section, such as a description, that is cut short to fit
+    pub fn text(mut self, priority: u8, name: &'static str, text: Option<&str>) -> Self {
+        self.sections.push(Section::Text {
+            priority,
+            name,
+            text: text.map(str::to_string),
+        });
+        self
+    }
+
+    /// Add a list of rendered items, oldest first, whose oldest items collapse into an
+    /// `omitted` count to fit
+    pub fn latest(mut self, priority: u8, name: &'static str, items: Vec<String>) -> Self {
+        self.sections.push(Section::List {
+            priority,
+            name,
+            items,
+            keep_latest: true,
+        });
+        self
+    }
+
+    /// Add a list of rendered items whose last items collapse into an `omitted` count to fit
+    pub fn leading(mut self, priority: u8, name: &'static str, items: Vec<String>) -> Self {
+        self.sections.push(Section::List {
+            priority,
+            name,
+            items,
+            keep_latest: false,
+        });
+        self
+    }
+
+    /// Fit the sections into the budget and join them in document order
+    pub fn build(self) -> String {
+        let fixed: usize = self
+            .sections
+            .iter()
+            .map(|section| match section {
+                Section::Fixed(xml) => xml.chars().count(),
+                _ => 0,
+            })
+            .sum();
+        let mut remaining = self.max_chars.saturating_sub(fixed);
+        let mut elastic: Vec<(u8, usize)> = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter_map(|(index, section)| match section {
+                Section::Fixed(_) => None,
+                Section::Text { priority, .. } | Section::List { priority, .. } => {
+                    Some((*priority, index))
+                }
+            })
+            .collect();
+        elastic.sort_by_key(|(priority, _)| *priority);
+
+        let mut rendered = vec![String::new(); self.sections.len()];
+        for (_, index) in elastic {
+            let xml = match &self.sections[index] {
+                Section::Fixed(_) => unreachable!(),
+                Section::Text { name, text, .. } => render_text(name, text.as_deref(), remaining),
+                Section::List {
+                    name,
+                    items,
+                    keep_latest,
+                    ..
+                } => render_list(name, items, *keep_latest, remaining),
+            };
+            remaining = remaining.saturating_sub(xml.chars().count());
+            rendered[index] = xml;
+        }
+        self.sections
+            .into_iter()
+            .zip(rendered)
+            .map(|(section, rendered)| match section {
+                Section::Fixed(xml) => xml,
+                _ => rendered,
+            })
+            .collect()
+    }
+}
+
+/// Render a text section in at most `max_chars` characters, if it can be done at all
+fn render_text(name: &str, text: Option<&str>, max_chars: usize) -> String {
+    let Some(text) = text else {
+        return format!("<{0}>\n</{0}>\n", name);
+    };
+    let escaped = escape_xml(text);
+    let whole = format!("<{0}>\n{1}\n</{0}>\n", name, escaped);
+    if whole.chars().count() <= max_chars {
+        return whole;
+    }
+    let frame = format!(
+        "<{0} truncated=\"true\">\n\n... (truncated) ...\n</{0}>\n",
+        name
+    );
+    let room = max_chars.saturating_sub(frame.chars().count());
+    format!(
+        "<{0} truncated=\"true\">\n{1}\n... (truncated) ...\n</{0}>\n",
+        name,
+        cut_escaped(&escaped, room)
+    )
+}
+
+/// Render a list section in at most `max_chars` characters, keeping its latest or leading items
+fn render_list(name: &str, items: &[String], keep_latest: bool, max_chars: usize) -> String {
+    if items.is_empty() {
+        return format!("<{0}></{0}>\n", name);
+    }
+    let length = |item: &String| item.chars().count();
+    let whole =
+        format!("<{0}>\n</{0}>\n", name).chars().count() + items.iter().map(length).sum::<usize>();
+    if whole <= max_chars {
+        return format!("<{0}>\n{1}</{0}>\n", name, items.concat());
+    }
+    let frame = format!(
+        "<{0} truncated=\"true\" omitted=\"{1}\">\n</{0}>\n",
+        name,
+        items.len()
+    );
+    let mut room = max_chars.saturating_sub(frame.chars().count());
+    let mut fits = |item: &&String| {
+        let length = length(item);
+        (length <= room).then(|| room -= length).is_some()
+    };
+    let kept = if keep_latest {
+        let kept = items.iter().rev().take_while(&mut fits).count();
+        &items[items.len() - kept..]
+    } else {
+        let kept = items.iter().take_while(&mut fits).count();
+        &items[..kept]
+    };
+    format!(
+        "<{0} truncated=\"true\" omitted=\"{1}\">\n{2}</{0}>\n",
+        name,
+        items.len() - kept.len(),
+        kept.concat()
+    )
+}
+
+/// The first `max_chars` characters of escaped text, without splitting an entity
+fn cut_escaped(escaped: &str, max_chars: usize) -> &str {
+    let end = escaped
+        .char_indices()
+        .nth(max_chars)
+        .map_or(escaped.len(), |(index, _)| index);
+    let cut = &escaped[..end];
+    match cut.rfind('&') {
+        Some(amp) if end < escaped.len() && !cut[amp..].contains(';') => &cut[..amp],
+        _ => cut,
+    }
+}
+
+/// Format a timestamp the way GitHub does, e.g. `2023-01-01T00:00:00Z`
+fn timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Format attributes as ` name="value"` pairs
+fn format_attributes(attributes: &[(&str, String)]) -> String {
+    attributes
+        .iter()
+        .map(|(name, value)| format!(" {}=\"{}\"", name, escape_xml(value)))
+        .collect()
+}
+
 /// Escape XML special characters
 fn escape_xml(input: &str) -> String {
     input
@@ -573,6 +1236,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn patch_excerpts_end_on_a_line() {
+        assert_eq!(("+a\n+b".to_string(), false), excerpt_patch("+a\n+b", 5));
+        assert_eq!(("+a".to_string(), true), excerpt_patch("+a\n+b", 4));
+        assert_eq!(("+abc".to_string(), true), excerpt_patch("+abcdef", 4));
+        assert_eq!((String::new(), true), excerpt_patch("+a", 0));
+    }
+
+    #[test]
+    fn text_sections_are_cut_to_fit() {
+        assert_eq!(
+            "<description>\na &amp; b\n</description>\n",
+            render_text("description", Some("a & b"), 100)
+        );
+        let xml = render_text("description", Some(&"x".repeat(200)), 100);
+        assert!(xml.starts_with("<description truncated=\"true\">\nxxx"));
+        assert!(xml.ends_with("x\n... (truncated) ...\n</description>\n"));
+        assert_eq!(100, xml.chars().count());
+    }
+
+    #[test]
+    fn cuts_do_not_split_entities() {
+        assert_eq!("a ", cut_escaped("a &amp; b", 4));
+        assert_eq!("a &amp;", cut_escaped("a &amp; b", 7));
+        assert_eq!("a &amp; b", cut_escaped("a &amp; b", 20));
+    }
+
+    #[test]
+    fn lists_collapse_into_an_omitted_count() {
+        let items: Vec<String> = (1..=3).map(|i| format!("  <c>{:040}</c>\n", i)).collect();
+        assert_eq!(
+            format!("<l>\n{}</l>\n", items.concat()),
+            render_list("l", &items, true, 1000)
+        );
+        let room = "<l truncated=\"true\" omitted=\"3\">\n</l>\n".len() + 2 * items[0].len();
+        assert_eq!(
+            format!(
+                "<l truncated=\"true\" omitted=\"1\">\n{}</l>\n",
+                items[1..].concat()
+            ),
+            render_list("l", &items, true, room)
+        );
+        assert_eq!(
+            format!(
+                "<l truncated=\"true\" omitted=\"1\">\n{}</l>\n",
+                items[..2].concat()
+            ),
+            render_list("l", &items, false, room)
+        );
+        assert_eq!("<l></l>\n", render_list("l", &[], true, 0));
+    }
+
+    #[test]
+    fn context_fills_sections_in_priority_order() {
+        let xml = Context::new(25)
+            .fixed("<h/>\n")
+            .text(1, "t", Some(&"x".repeat(200)))
+            .latest(0, "l", vec!["  <c>1</c>\n".to_string()])
+            .fixed("<f/>\n")
+            .build();
+        assert!(xml.starts_with("<h/>\n<t truncated=\"true\">\nxxx"));
+        assert!(xml.ends_with("</t>\n<l>\n  <c>1</c>\n</l>\n<f/>\n"));
+        assert!(xml.chars().count() <= 25 * CHARS_PER_TOKEN);
+    }
+
     #[test]
     fn build_notification_context_xml() {
         let xml =
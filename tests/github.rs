@@ -0,0 +1,557 @@
+mod support;
+
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use futures::TryStreamExt;
+
+use ghai::http::GitHubClient;
+use ghai::{
+    Action, CommentFetcher, Error, Issue, IssueComment, Notification, NotificationReason,
+    PullRequest, RunStatus, SubjectType,
+};
+
+use support::{MockGitHub, TOKEN};
+
+fn client(mock: &MockGitHub) -> GitHubClient {
+    GitHubClient::with_token(TOKEN).with_base_url(mock.url())
+}
+
+fn serve_notifications(mock: &MockGitHub) {
+    mock.on("GET", "/notifications")
+        .query("page", "2")
+        .fixture("notifications_page2.json");
+    mock.on("GET", "/notifications")
+        .header(
+            "Link",
+            r#"<{{base_url}}/notifications?page=2>; rel="next", <{{base_url}}/notifications?page=2>; rel="last""#,
+        )
+        .fixture("notifications_page1.json");
+}
+
+//////////////////////////////////////////// notifications /////////////////////////////////////////
+
+#[tokio::test]
+async fn fetch_all_follows_link_header() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&client(&mock), false, false, None, None)
+        .await
+        .unwrap();
+
+    let ids = notifications
+        .iter()
+        .map(|n| n.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["103", "102", "101"], ids);
+    assert_eq!(SubjectType::PullRequest, notifications[2].subject.r#type);
+    assert_eq!(NotificationReason::ReviewRequested, notifications[2].reason);
+    let requests = mock.requests_to("GET", "/notifications");
+    assert_eq!(2, requests.len());
+    assert_eq!(None, requests[0].param("page"));
+    assert_eq!(Some("2"), requests[1].param("page"));
+}
+
+#[tokio::test]
+async fn token_stays_on_the_api_origin() {
+    let mock = MockGitHub::start().await;
+    // The same server under another name is another origin.
+    let elsewhere = mock.url().replace("127.0.0.1", "localhost");
+    mock.on("GET", "/notifications")
+        .query("page", "2")
+        .fixture("notifications_page2.json");
+    mock.on("GET", "/notifications")
+        .header(
+            "Link",
+            &format!(r#"<{}/notifications?page=2>; rel="next""#, elsewhere),
+        )
+        .fixture("notifications_page1.json");
+
+    let notifications = Notification::fetch_all::<Utc>(&client(&mock), false, false, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(3, notifications.len());
+    let requests = mock.requests_to("GET", "/notifications");
+    assert_eq!(2, requests.len());
+    assert_eq!(
+        Some(format!("Bearer {}", TOKEN).as_str()),
+        requests[0].header("Authorization")
+    );
+    assert_eq!(None, requests[1].header("Authorization"));
+}
+
+#[tokio::test]
+async fn fetch_all_sends_filters_and_headers() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications").body("[]");
+    let since = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+
+    let notifications = Notification::fetch_all(&client(&mock), true, true, Some(since), None)
+        .await
+        .unwrap();
+
+    assert!(notifications.is_empty());
+    let request = &mock.requests()[0];
+    assert_eq!(Some("true"), request.param("all"));
+    assert_eq!(Some("true"), request.param("participating"));
+    assert_eq!(Some("2024-04-01T00:00:00+00:00"), request.param("since"));
+    assert_eq!(None, request.param("before"));
+    assert_eq!(
+        Some(format!("Bearer {}", TOKEN).as_str()),
+        request.header("Authorization")
+    );
+    assert_eq!(
+        Some("application/vnd.github+json"),
+        request.header("Accept")
+    );
+    assert_eq!(Some("ghai"), request.header("User-Agent"));
+}
+
+#[tokio::test]
+async fn mark_as_read_patches_thread() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    mock.on("PATCH", "/notifications/threads/101").status(205);
+    let github = client(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+    let thread = notifications.iter().find(|n| n.id == "101").unwrap();
+    thread.mark_as_read(&github).await.unwrap();
+
+    let patches = mock.requests_to("PATCH", "/notifications/threads/101");
+    assert_eq!(1, patches.len());
+    assert_eq!(
+        Some(format!("Bearer {}", TOKEN).as_str()),
+        patches[0].header("Authorization")
+    );
+}
+
+#[tokio::test]
+async fn thread_actions_hit_their_endpoints() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    mock.on("DELETE", "/notifications/threads/101").status(204);
+    mock.on("DELETE", "/notifications/threads/101/subscription")
+        .status(204);
+    mock.on("PUT", "/notifications/threads/102/subscription")
+        .body(r#"{"subscribed": false, "ignored": true}"#);
+    mock.on("PUT", "/user/starred/rescrv/ghai").status(204);
+    mock.on("POST", "/repos/rescrv/ghai/issues/7/labels")
+        .body("[]");
+    let github = client(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+    let pr_thread = notifications.iter().find(|n| n.id == "101").unwrap();
+    let issue_thread = notifications.iter().find(|n| n.id == "102").unwrap();
+    pr_thread.mark_as_done(&github).await.unwrap();
+    pr_thread.unsubscribe(&github).await.unwrap();
+    issue_thread.ignore(&github).await.unwrap();
+    pr_thread.star_repository(&github).await.unwrap();
+    pr_thread
+        .add_labels(&github, &["triaged".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/notifications/threads/101")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/notifications/threads/101/subscription")
+            .len()
+    );
+    let ignore = &mock.requests_to("PUT", "/notifications/threads/102/subscription")[0];
+    assert_eq!(
+        serde_json::json!({"ignored": true}),
+        serde_json::from_str::<serde_json::Value>(&ignore.body).unwrap()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PUT", "/user/starred/rescrv/ghai").len()
+    );
+    let labels = &mock.requests_to("POST", "/repos/rescrv/ghai/issues/7/labels")[0];
+    assert_eq!(
+        serde_json::json!({"labels": ["triaged"]}),
+        serde_json::from_str::<serde_json::Value>(&labels.body).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn undo_actions_hit_their_endpoints() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    mock.on("PUT", "/notifications/threads/101/subscription")
+        .body(r#"{"subscribed": true, "ignored": false}"#);
+    mock.on("GET", "/user/starred/rescrv/ghai").status(204);
+    mock.on("DELETE", "/user/starred/rescrv/ghai").status(204);
+    mock.on("GET", "/repos/rescrv/ghai/issues/7/labels")
+        .body(r#"["triaged"]"#);
+    mock.on(
+        "DELETE",
+        "/repos/rescrv/ghai/issues/7/labels/needs%20review",
+    )
+    .body("[]");
+    let github = client(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+    let pr_thread = notifications.iter().find(|n| n.id == "101").unwrap();
+    pr_thread.resubscribe(&github).await.unwrap();
+    assert!(pr_thread.is_repository_starred(&github).await.unwrap());
+    pr_thread.unstar_repository(&github).await.unwrap();
+    let labels = pr_thread.fetch_labels(&github).await.unwrap();
+    assert_eq!(
+        vec!["triaged"],
+        labels.iter().map(|l| l.name()).collect::<Vec<_>>()
+    );
+    pr_thread
+        .remove_label(&github, "needs review")
+        .await
+        .unwrap();
+
+    let resubscribe = &mock.requests_to("PUT", "/notifications/threads/101/subscription")[0];
+    assert_eq!(
+        serde_json::json!({"subscribed": true, "ignored": false}),
+        serde_json::from_str::<serde_json::Value>(&resubscribe.body).unwrap()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/user/starred/rescrv/ghai")
+            .len()
+    );
+    // An unstarred repository is reported rather than treated as an error.
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    let github = client(&mock);
+    assert!(!pr_thread.is_repository_starred(&github).await.unwrap());
+}
+
+#[tokio::test]
+async fn fetch_subject_follows_subject_url() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7")
+        .fixture("pull_request.json");
+    mock.on("GET", "/repos/rescrv/ghai/issues/8")
+        .fixture("issue.json");
+    let github = client(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+    let pr_thread = notifications.iter().find(|n| n.id == "101").unwrap();
+    let issue_thread = notifications.iter().find(|n| n.id == "102").unwrap();
+
+    let pr = pr_thread.fetch_pull_request(&github).await.unwrap();
+    assert_eq!(7, pr.number);
+    let issue = issue_thread.fetch_issue(&github).await.unwrap();
+    assert_eq!(8, issue.number);
+
+    match pr_thread.fetch_issue(&github).await {
+        Err(Error::UnexpectedSubject { expected, actual }) => {
+            assert_eq!("Issue", expected);
+            assert_eq!("PullRequest", actual);
+        }
+        other => panic!(
+            "expected UnexpectedSubject, got {:?}",
+            other.map(|i| i.number)
+        ),
+    }
+}
+
+#[tokio::test]
+async fn fetch_comments_passes_since() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/repos/rescrv/ghai/issues/8")
+        .fixture("issue.json");
+    mock.on("GET", "/repos/rescrv/ghai/issues/8/comments")
+        .fixture("issue_comments.json");
+    let github = client(&mock);
+
+    let issue: Issue = github
+        .get_json(&github.api_url("/repos/rescrv/ghai/issues/8"))
+        .await
+        .unwrap();
+    let since = Utc.with_ymd_and_hms(2024, 4, 30, 8, 0, 0).unwrap();
+    let comments: Vec<IssueComment> = issue.fetch_comments(&github, Some(since)).await.unwrap();
+
+    assert_eq!(1, comments.len());
+    assert_eq!(5002, comments[0].id);
+    let requests = mock.requests_to("GET", "/repos/rescrv/ghai/issues/8/comments");
+    assert_eq!(
+        Some("2024-04-30T08:00:00+00:00"),
+        requests[0].param("since")
+    );
+}
+
+#[tokio::test]
+async fn fetch_reviews_since_last_read() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7/reviews")
+        .fixture("pull_request_reviews.json");
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7/comments")
+        .fixture("pull_request_review_comments.json");
+    let github = client(&mock);
+    let pr: PullRequest = serde_json::from_str(&mock.fixture("pull_request.json")).unwrap();
+
+    let since = Utc.with_ymd_and_hms(2024, 4, 30, 8, 0, 0).unwrap();
+    let reviews = pr.fetch_reviews(&github, Some(since)).await.unwrap();
+    assert_eq!(vec![6002], reviews.iter().map(|r| r.id).collect::<Vec<_>>());
+    assert_eq!(
+        2,
+        pr.fetch_reviews::<Utc>(&github, None).await.unwrap().len()
+    );
+
+    let comments = pr
+        .fetch_review_comments(&github, Some(since))
+        .await
+        .unwrap();
+    assert_eq!(Some("src/http.rs"), comments[0].path.as_deref());
+    let requests = mock.requests_to("GET", "/repos/rescrv/ghai/pulls/7/comments");
+    assert_eq!(
+        Some("2024-04-30T08:00:00+00:00"),
+        requests[0].param("since")
+    );
+}
+
+#[tokio::test]
+async fn poll_is_conditional_on_last_modified() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .header("Last-Modified", "Wed, 01 May 2024 12:00:00 GMT")
+        .header("X-Poll-Interval", "30")
+        .fixture("notifications_page1.json")
+        .times(1);
+    mock.on("GET", "/notifications")
+        .status(304)
+        .header("X-Poll-Interval", "45");
+    let github = client(&mock);
+
+    let first = Notification::poll(&github, None).await.unwrap();
+    let ids = first
+        .items
+        .unwrap()
+        .iter()
+        .map(|n| n.id.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["102", "101"], ids);
+    assert_eq!(Some(Duration::from_secs(30)), first.poll_interval);
+    let last_modified = first.last_modified.unwrap();
+    assert_eq!("Wed, 01 May 2024 12:00:00 GMT", last_modified);
+
+    let second = Notification::poll(&github, Some(&last_modified))
+        .await
+        .unwrap();
+    assert!(second.items.is_none());
+    assert_eq!(
+        Some(last_modified.as_str()),
+        second.last_modified.as_deref()
+    );
+    assert_eq!(Some(Duration::from_secs(45)), second.poll_interval);
+    let requests = mock.requests_to("GET", "/notifications");
+    assert_eq!(None, requests[0].header("If-Modified-Since"));
+    assert_eq!(
+        Some("Wed, 01 May 2024 12:00:00 GMT"),
+        requests[1].header("If-Modified-Since")
+    );
+}
+
+/////////////////////////////////////////////// issues /////////////////////////////////////////////
+
+#[tokio::test]
+async fn fetch_user_issues_sends_query() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/issues").fixture("user_issues.json");
+
+    let issues = Issue::fetch_user_issues::<Utc>(
+        &client(&mock),
+        Some("assigned".to_string()),
+        Some("open".to_string()),
+        Some("bug,ui".to_string()),
+        None,
+        Some("asc".to_string()),
+        None,
+        Some(50),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, issues.len());
+    let request = &mock.requests()[0];
+    assert_eq!(Some("assigned"), request.param("filter"));
+    assert_eq!(Some("open"), request.param("state"));
+    assert_eq!(Some("bug,ui"), request.param("labels"));
+    assert_eq!(None, request.param("sort"));
+    assert_eq!(Some("asc"), request.param("direction"));
+    assert_eq!(Some("50"), request.param("per_page"));
+}
+
+/////////////////////////////////////////////// actions ////////////////////////////////////////////
+
+#[tokio::test]
+async fn fetch_runs_and_jobs() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/repos/rescrv/ghai/actions/runs")
+        .fixture("runs.json");
+    mock.on("GET", "/repos/rescrv/ghai/actions/runs/30433642/jobs")
+        .fixture("jobs.json");
+    let github = client(&mock);
+
+    let runs = Action::fetch_all(
+        &github,
+        "rescrv".to_string(),
+        "ghai".to_string(),
+        Some("rescrv".to_string()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(2, runs.len());
+    assert_eq!(30433643, runs[0].id);
+    assert_eq!(Some(RunStatus::InProgress), runs[0].status);
+    assert_eq!(Some(RunStatus::Completed), runs[1].status);
+    let request = &mock.requests()[0];
+    assert_eq!(Some("rescrv"), request.param("actor"));
+
+    let jobs = runs[1].fetch_jobs(&github).await.unwrap();
+    assert_eq!(1, jobs.len());
+    assert_eq!("build", jobs[0].name);
+}
+
+/////////////////////////////////////////////// errors /////////////////////////////////////////////
+
+#[tokio::test]
+async fn statuses_map_to_errors() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications").status(401).body(
+        r#"{"message": "Bad credentials", "documentation_url": "https://docs.github.com/rest"}"#,
+    );
+    let github = client(&mock);
+
+    match Notification::fetch_all::<Utc>(&github, false, false, None, None).await {
+        Err(Error::Unauthorized(err)) => {
+            assert_eq!(401, err.status);
+            assert_eq!(Some("Bad credentials"), err.message.as_deref());
+        }
+        other => panic!("expected Unauthorized, got {:?}", other.map(|n| n.len())),
+    }
+
+    let result: Result<Issue, Error> = github
+        .get_json(&github.api_url("/repos/rescrv/ghai/issues/404"))
+        .await;
+    match result {
+        Err(Error::NotFound(err)) => {
+            assert!(err.url.ends_with("/repos/rescrv/ghai/issues/404"));
+        }
+        other => panic!("expected NotFound, got {:?}", other.map(|i| i.number)),
+    }
+}
+
+#[tokio::test]
+async fn decode_errors_carry_path() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications").body(r#"[{"id": 7}]"#);
+
+    match Notification::fetch_all::<Utc>(&client(&mock), false, false, None, None).await {
+        Err(Error::Decode { path, .. }) => assert_eq!("[0].id", path),
+        other => panic!("expected Decode, got {:?}", other.map(|n| n.len())),
+    }
+}
+
+#[cfg(feature = "lenient")]
+#[tokio::test]
+async fn unknown_fields_are_left_as_warnings() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/repos/rescrv/blue/releases/3001")
+        .body(
+            mock.fixture("release.json")
+                .replacen('{', r#"{"brand_new_field": true,"#, 1),
+        );
+    let github = client(&mock);
+
+    let release: ghai::Release = github
+        .get_json(&github.api_url("/repos/rescrv/blue/releases/3001"))
+        .await
+        .unwrap();
+
+    assert_eq!("v0.7.0", release.tag_name);
+    let warnings = github.take_warnings();
+    assert_eq!(1, warnings.len());
+    assert!(warnings[0].contains("brand_new_field"), "{:?}", warnings);
+    assert!(github.take_warnings().is_empty());
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications").status(503).times(1);
+    mock.on("GET", "/notifications")
+        .header("X-RateLimit-Limit", "5000")
+        .header("X-RateLimit-Remaining", "4321")
+        .header("X-RateLimit-Reset", "4102444800")
+        .fixture("notifications_release_only.json");
+    let github = client(&mock);
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(1, notifications.len());
+    assert_eq!(2, mock.requests().len());
+    assert_eq!(4321, github.rate_limit().unwrap().remaining);
+}
+
+// Paused time skips the minute-long wait a secondary rate limit asks for.
+#[tokio::test(start_paused = true)]
+async fn secondary_rate_limits_are_retried() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .status(403)
+        .body(r#"{"message": "You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#)
+        .times(1);
+    mock.on("GET", "/notifications")
+        .fixture("notifications_release_only.json");
+    let github = client(&mock);
+    let started = tokio::time::Instant::now();
+
+    let notifications = Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(1, notifications.len());
+    assert_eq!(2, mock.requests().len());
+    assert!(started.elapsed() >= Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn paginate_stream_stops_at_limit() {
+    let mock = MockGitHub::start().await;
+    serve_notifications(&mock);
+    let github = client(&mock);
+
+    let notifications = github
+        .paginate_stream::<Vec<Notification>>(&github.api_url("/notifications"), Some(1))
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    assert_eq!(1, notifications.len());
+    assert_eq!(1, mock.requests().len());
+}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn cases() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/policy_cases")
+}
+
+/// Run `ghai-policy-test` with the keyword fake over `dir`
+fn policy_test(dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ghai-policy-test"))
+        .arg("--cases")
+        .arg(dir)
+        .arg("--fake-llm")
+        .arg(cases().join("policies.txt"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn passing_cases() {
+    let output = policy_test(&cases());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("✓ dependabot-bump"), "{}", stdout);
+    assert!(stdout.contains("✓ release"), "{}", stdout);
+    assert!(stdout.contains("✓ security-advisory"), "{}", stdout);
+    assert!(stdout.contains("3 cases: 3 passed, 0 failed"), "{}", stdout);
+}
+
+#[test]
+fn failing_cases_report_differences() {
+    let dir = std::env::temp_dir().join(format!("ghai-policy-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["dependabot-bump", "release"] {
+        std::fs::copy(
+            cases().join(format!("{}.xml", name)),
+            dir.join(format!("{}.xml", name)),
+        )
+        .unwrap();
+    }
+    std::fs::write(
+        dir.join("dependabot-bump.json"),
+        r#"{"action": "mark-read", "label": ["dependencies"]}"#,
+    )
+    .unwrap();
+
+    let output = policy_test(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Some(1), output.status.code(), "{}", stdout);
+    assert!(
+        stdout
+            .contains("✗ dependabot-bump\n    action: expected \"mark-read\", got \"mark-done\"\n"),
+        "{}",
+        stdout
+    );
+    // A context without an expected decision fails rather than passing vacuously.
+    assert!(stdout.contains("✗ release\n    error:"), "{}", stdout);
+    assert!(stdout.contains("2 cases: 0 passed, 2 failed"), "{}", stdout);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
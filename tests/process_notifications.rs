@@ -0,0 +1,902 @@
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+use chrono::Utc;
+use ghai::audit::{AuditLog, DecisionSource};
+use ghai::cache::{fingerprint, CachedDecision, DecisionCache};
+use ghai::http::GitHubClient;
+use ghai::policy::{Decision, DecisionAction, Explanation, Priority};
+use ghai::Notification;
+
+use support::{MockGitHub, TOKEN};
+
+/// An empty directory for one test's config and cache
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ghai-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run `ghai-process-notifications` against the mock, keeping its files under `scratch`
+fn process_notifications(mock: &MockGitHub, scratch: &Path, args: &[&str]) -> Output {
+    command(mock, scratch, args).output().unwrap()
+}
+
+fn command(mock: &MockGitHub, scratch: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ghai-process-notifications"));
+    command
+        .args(args)
+        .env("GHAI_API_URL", mock.url())
+        .env("GITHUB_TOKEN", TOKEN)
+        .env("CLAUDIUS_API_KEY", "unused")
+        .env("OPENAI_API_KEY", "sk-test")
+        .env("GHAI_CONFIG", scratch.join("config.json"))
+        .env("XDG_CACHE_HOME", scratch.join("cache"))
+        .env("XDG_STATE_HOME", scratch.join("state"))
+        .env_remove("GH_HOST");
+    command
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn empty_inbox() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications").body("[]");
+
+    let output = process_notifications(&mock, &scratch("empty-inbox"), &["--no-confirm"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Processing 0 notifications"), "{}", stdout);
+    assert!(stdout.contains("Total processed: 0"), "{}", stdout);
+    assert_eq!(1, mock.requests().len());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unsupported_subjects_are_skipped() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .fixture("notifications_invitation_only.json");
+
+    let output = process_notifications(
+        &mock,
+        &scratch("unsupported-subjects-are-skipped"),
+        &["--no-confirm"],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(
+        stderr.contains("Skipping unsupported notification type: RepositoryInvitation"),
+        "{}",
+        stderr
+    );
+    assert!(stdout.contains("Skipped: 1"), "{}", stdout);
+    assert!(mock
+        .requests_to("PATCH", "/notifications/threads/104")
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn github_errors_fail_the_run() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .status(401)
+        .body(r#"{"message": "Bad credentials"}"#);
+
+    let output = process_notifications(&mock, &scratch("github-errors-fail-the-run"), &[]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Bad credentials"), "{}", stderr);
+}
+
+/// Serve a pull request and an issue notification, and a model that decides nothing
+fn serve_triage(mock: &MockGitHub) {
+    mock.on("GET", "/notifications")
+        .fixture("notifications_page1.json");
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7")
+        .fixture("pull_request.json");
+    mock.on("GET", "/repos/rescrv/ghai/issues/7/comments")
+        .fixture("pull_request_comments.json");
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7/reviews")
+        .fixture("pull_request_reviews.json");
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7/comments")
+        .fixture("pull_request_review_comments.json");
+    mock.on("GET", "/repos/rescrv/ghai/pulls/7/files")
+        .fixture("pull_request_files.json");
+    mock.on("GET", "/repos/rescrv/ghai/issues/8")
+        .fixture("issue.json");
+    mock.on("GET", "/repos/rescrv/ghai/issues/8/comments")
+        .fixture("issue_comments.json");
+    mock.on("POST", "/v1/chat/completions")
+        .body_contains("output_json")
+        .fixture("chat_policy.json");
+    mock.on("POST", "/v1/chat/completions")
+        .fixture("chat_summary.json");
+    mock.on("PATCH", "/notifications/threads/101").status(205);
+    mock.on("PATCH", "/notifications/threads/102").status(205);
+}
+
+/// Arguments that triage with the mock's chat completions endpoint, marking everything read
+fn triage_args<'a>(llm_url: &'a str, extra: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec![
+        "--no-confirm",
+        "--mark-read-by-default",
+        "--llm-backend",
+        "openai",
+        "--llm-url",
+        &llm_url,
+        "--llm-model",
+        "triage-test",
+    ];
+    args.extend_from_slice(extra);
+    args
+}
+
+fn triage(mock: &MockGitHub, scratch: &Path, extra: &[&str]) -> Output {
+    let llm_url = format!("{}/v1", mock.url());
+    process_notifications(mock, scratch, &triage_args(&llm_url, extra))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn triage_with_openai_compatible_backend() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+
+    let output = triage(&mock, &scratch("triage"), &[]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Marked as read: 2"), "{}", stdout);
+    let chats = mock.requests_to("POST", "/v1/chat/completions");
+    assert_eq!(4, chats.len());
+    assert_eq!(Some("Bearer sk-test"), chats[0].header("Authorization"));
+    // Each notification gets a policy request followed by a summary request.
+    let bodies = chats
+        .iter()
+        .map(|c| serde_json::from_str::<serde_json::Value>(&c.body).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!("triage-test", bodies[0]["model"]);
+    assert_eq!("output_json", bodies[0]["tool_choice"]["function"]["name"]);
+    assert_eq!(serde_json::Value::Null, bodies[1]["tools"]);
+    let pr_prompt = bodies[2]["messages"].to_string();
+    assert!(pr_prompt.contains("Add pagination to fetchers"));
+    // Only the review submitted since the thread was last read is shown.
+    assert!(
+        pr_prompt.contains("<state>CHANGES_REQUESTED</state>"),
+        "{}",
+        pr_prompt
+    );
+    assert!(
+        !pr_prompt.contains("Looks like a good start"),
+        "{}",
+        pr_prompt
+    );
+    assert!(
+        pr_prompt.contains("<path>src/http.rs</path>"),
+        "{}",
+        pr_prompt
+    );
+    assert!(pr_prompt.contains("on a 503 &lt;loop&gt;"), "{}", pr_prompt);
+    // Changed files are opt-in.
+    assert!(!pr_prompt.contains("<file path="), "{}", pr_prompt);
+    assert!(mock
+        .requests_to("GET", "/repos/rescrv/ghai/pulls/7/files")
+        .is_empty());
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/101")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/102")
+            .len()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quitting_still_reports() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    let llm_url = format!("{}/v1", mock.url());
+    let mut args = triage_args(&llm_url, &[]);
+    args.retain(|arg| *arg != "--no-confirm");
+
+    let mut child = command(&mock, &scratch("quitting-still-reports"), &args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(child.stdin.as_mut().unwrap(), b"q\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Exiting at user request"), "{}", stdout);
+    assert!(stdout.contains("Processing Complete!"), "{}", stdout);
+    assert!(mock
+        .requests_to("PATCH", "/notifications/threads/101")
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn releases_are_described_to_the_model() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .fixture("notifications_release_only.json");
+    mock.on("GET", "/repos/rescrv/blue/releases/3001")
+        .fixture("release.json");
+    mock.on("POST", "/v1/chat/completions")
+        .body_contains("output_json")
+        .fixture("chat_policy.json");
+    mock.on("POST", "/v1/chat/completions")
+        .fixture("chat_summary.json");
+    mock.on("PATCH", "/notifications/threads/103").status(205);
+
+    let output = triage(&mock, &scratch("releases"), &[]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Marked as read: 1"), "{}", stdout);
+    let chats = mock.requests_to("POST", "/v1/chat/completions");
+    assert_eq!(2, chats.len());
+    let policy: serde_json::Value = serde_json::from_str(&chats[0].body).unwrap();
+    let prompt = policy["messages"].to_string();
+    assert!(prompt.contains("<tag>v0.7.0</tag>"), "{}", prompt);
+    assert!(
+        prompt.contains("streaming pagination &amp; fixes"),
+        "{}",
+        prompt
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/103")
+            .len()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn forbidden_security_alerts_are_skipped() {
+    let mock = MockGitHub::start().await;
+    mock.on("GET", "/notifications")
+        .fixture("notifications_security_alert.json");
+    mock.on("GET", "/repos/rescrv/blue/dependabot/alerts")
+        .status(403)
+        .body(r#"{"message": "Resource not accessible by integration"}"#);
+    mock.on("GET", "/repos/rescrv/blue/releases/3001")
+        .fixture("release.json");
+    mock.on("POST", "/v1/chat/completions")
+        .body_contains("output_json")
+        .fixture("chat_policy.json");
+    mock.on("POST", "/v1/chat/completions")
+        .fixture("chat_summary.json");
+    mock.on("PATCH", "/notifications/threads/103").status(205);
+
+    let output = triage(&mock, &scratch("forbidden-alerts"), &["--jobs", "2"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(
+        stderr.contains("Skipping notification whose subject is unavailable: forbidden"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Resource not accessible by integration"),
+        "{}",
+        stderr
+    );
+    // The release after the alert is still decided and carried out.
+    assert!(stdout.contains("Marked as read: 1"), "{}", stdout);
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/103")
+            .len()
+    );
+    assert!(mock
+        .requests_to("PATCH", "/notifications/threads/105")
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changed_files_and_patches_are_opt_in() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+
+    let output = triage(&mock, &scratch("patch-budget"), &["--patch-budget", "120"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    let chats = mock.requests_to("POST", "/v1/chat/completions");
+    let policy: serde_json::Value = serde_json::from_str(&chats[2].body).unwrap();
+    let prompt = policy["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|message| message["content"].as_str())
+        .collect::<String>();
+    assert!(
+        prompt.contains(
+            r#"<file path="docs/logo.png" status="added" additions="0" deletions="0" />"#
+        ),
+        "{}",
+        prompt
+    );
+    // The budget is shared between the two patches: the first is cut at a line boundary, and the
+    // README's fits whole in what is left.
+    assert!(
+        prompt.contains("<patch truncated=\"true\">\n@@ -40,6"),
+        "{}",
+        prompt
+    );
+    assert!(!prompt.contains("TODO: pagination"), "{}", prompt);
+    assert!(prompt.contains("<patch>\n@@ -1,3 +1,6 @@"), "{}", prompt);
+    assert_eq!(
+        1,
+        mock.requests_to("GET", "/repos/rescrv/ghai/pulls/7/files")
+            .len()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_decisions_keep_their_order() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+
+    let output = triage(&mock, &scratch("jobs"), &["--jobs", "4", "--detailed"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Marked as read: 2"), "{}", stdout);
+    assert_eq!(4, mock.requests_to("POST", "/v1/chat/completions").len());
+    // Notifications are reported in the order a sequential run reports them, each after its own
+    // progress line, however the decisions finished.
+    let sequence = |stdout: &str| {
+        stdout
+            .lines()
+            .filter(|line| line.starts_with("🔄") || line.starts_with("   Title:"))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let sequential = triage(&mock, &scratch("jobs-sequential"), &["--detailed"]);
+    let expected = sequence(&String::from_utf8_lossy(&sequential.stdout));
+    assert_eq!(4, expected.len());
+    assert_eq!(expected, sequence(&stdout), "{}", stdout);
+
+    let output = triage(&mock, &scratch("jobs-zero"), &["--jobs", "0"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--jobs must be at least 1"), "{}", stderr);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spent_budgets_stop_model_calls() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+
+    let output = triage(
+        &mock,
+        &scratch("budget"),
+        &["--max-tokens-budget", "100", "--no-cache"],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    // The first policy request spends the budget, so neither a summary nor the second
+    // notification is sent to the model, and both fall back to --mark-read-by-default.
+    assert_eq!(1, mock.requests_to("POST", "/v1/chat/completions").len());
+    assert!(
+        stderr.contains("token budget of 100 is spent"),
+        "{}",
+        stderr
+    );
+    assert!(stdout.contains("Marked as read: 2"), "{}", stdout);
+    assert!(stdout.contains("Over budget: 1"), "{}", stdout);
+    assert!(
+        stdout.contains("Tokens (triage-test): 812 input, 9 output"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Estimated cost"), "{}", stdout);
+
+    let output = triage(&mock, &scratch("budget-cost"), &["--max-cost", "1"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("no price is known for triage-test"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn decisions_are_audited() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    let scratch = scratch("audit");
+
+    let output = triage(&mock, &scratch, &[]);
+    assert!(output.status.success());
+
+    let chats = mock.requests_to("POST", "/v1/chat/completions");
+    let body = serde_json::from_str::<serde_json::Value>(&chats[0].body).unwrap();
+    assert_eq!(
+        "string",
+        body["tools"][0]["function"]["parameters"]["properties"]["__rationale__"]["type"]
+    );
+    let log = AuditLog::at(scratch.join("state").join("ghai").join("audit.jsonl"));
+    let entries = log.entries().unwrap();
+    assert_eq!(2, entries.len());
+    let entry = entries.iter().find(|e| e.thread_id == "101").unwrap();
+    assert_eq!(vec!["mark-read".to_string()], entry.actions);
+    assert_eq!(DecisionSource::Model, entry.source);
+    assert_eq!(
+        Some("No rule applies to this thread."),
+        entry.explanation.rationale.as_deref()
+    );
+    assert_eq!(Some("triage-test"), entry.model.as_deref());
+    // The policy and summary requests together
+    assert_eq!(812 + 790, entry.usage.unwrap().input_tokens);
+
+    let audit = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ghai-audit"))
+            .args(args)
+            .env("XDG_STATE_HOME", scratch.join("state"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let all = audit(&["--repo", "rescrv/ghai", "--action", "mark-read"]);
+    assert!(all.contains("Add pagination to fetchers"), "{}", all);
+    assert!(
+        all.contains("rationale: No rule applies to this thread."),
+        "{}",
+        all
+    );
+    assert!(all.contains("2 entries"), "{}", all);
+    let none = audit(&["--action", "unsubscribe", "--json"]);
+    assert_eq!("", none);
+    let none = audit(&["--until", "2000-01-01"]);
+    assert!(none.contains("No matching entries"), "{}", none);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unchanged_threads_are_decided_from_cache() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    let scratch = scratch("cache");
+
+    let first = triage(&mock, &scratch, &[]);
+    assert!(first.status.success());
+    let second = triage(&mock, &scratch, &[]);
+
+    let stdout = String::from_utf8_lossy(&second.stdout);
+    assert!(second.status.success(), "{}", stdout);
+    assert!(stdout.contains("Decided from cache: 2"), "{}", stdout);
+    assert!(stdout.contains("Marked as read: 2"), "{}", stdout);
+    assert_eq!(4, mock.requests_to("POST", "/v1/chat/completions").len());
+    assert_eq!(
+        1,
+        mock.requests_to("GET", "/repos/rescrv/ghai/pulls/7").len()
+    );
+    assert!(scratch.join("cache/ghai/decisions/101.json").exists());
+
+    let third = triage(&mock, &scratch, &["--no-cache"]);
+    assert!(third.status.success());
+    assert_eq!(8, mock.requests_to("POST", "/v1/chat/completions").len());
+}
+
+/// Cache a decision for each served notification, as a triage run with no policies would
+///
+/// This stands in for scripting the model, whose output refers to policies by randomized masks.
+async fn seed_decisions(
+    mock: &MockGitHub,
+    scratch: &Path,
+    decide: impl Fn(&Notification) -> Decision,
+) {
+    let github = GitHubClient::with_token(TOKEN).with_base_url(mock.url());
+    let cache = DecisionCache::at(scratch.join("cache/ghai/decisions"));
+    for thread in Notification::fetch_all::<Utc>(&github, false, false, None, None)
+        .await
+        .unwrap()
+    {
+        let cached = CachedDecision {
+            decision: decide(&thread),
+            summary: format!("Summary of {}", thread.subject.title),
+            context: String::new(),
+            explanation: Explanation::default(),
+        };
+        cache
+            .put(&thread, &fingerprint("", "triage-test"), &cached)
+            .unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn decisions_are_carried_out() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    mock.on("DELETE", "/notifications/threads/101/subscription")
+        .status(204);
+    mock.on("PUT", "/notifications/threads/102/subscription")
+        .body(r#"{"subscribed": false, "ignored": true}"#);
+    mock.on("PUT", "/user/starred/rescrv/ghai").status(204);
+    mock.on("GET", "/repos/rescrv/ghai/issues/7/labels")
+        .body("[]");
+    mock.on("POST", "/repos/rescrv/ghai/issues/7/labels")
+        .status(403)
+        .body(r#"{"message": "Must have admin rights to Repository."}"#);
+    let scratch = scratch("decisions");
+    seed_decisions(&mock, &scratch, |thread| {
+        if thread.id == "101" {
+            Decision {
+                action: Some(DecisionAction::Unsubscribe),
+                priority: None,
+                label: vec!["triaged".to_string()],
+                star_repo: true,
+                apply_label: true,
+            }
+        } else {
+            Decision {
+                action: Some(DecisionAction::Ignore),
+                priority: None,
+                label: vec!["triaged".to_string()],
+                star_repo: false,
+                apply_label: false,
+            }
+        }
+    })
+    .await;
+
+    let output = triage(&mock, &scratch, &[]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Decided from cache: 2"), "{}", stdout);
+    assert!(stdout.contains("Unsubscribed: 1"), "{}", stdout);
+    assert!(stdout.contains("Ignored: 1"), "{}", stdout);
+    assert!(stdout.contains("Starred repositories: 1"), "{}", stdout);
+    // A refused label is reported without failing the run.
+    assert!(!stdout.contains("Labeled:"), "{}", stdout);
+    assert!(stderr.contains("Could not label it triaged"), "{}", stderr);
+    assert!(mock.requests_to("POST", "/v1/chat/completions").is_empty());
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/notifications/threads/101/subscription")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PUT", "/notifications/threads/102/subscription")
+            .len()
+    );
+    // Leaving a thread also marks it read.
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/101")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PATCH", "/notifications/threads/102")
+            .len()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rules_decide_without_the_model() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    mock.on("DELETE", "/notifications/threads/101").status(204);
+    let scratch = scratch("rules");
+    let policies = scratch.join("policies.txt");
+    std::fs::write(
+        &policies,
+        "when type == \"PullRequest\" and state == \"open\" and not draft output {\"action\": \"mark-done\"}\n\
+         If the issue is about pagination, output {\"action\": \"mark-read\"}\n",
+    )
+    .unwrap();
+
+    let output = triage(&mock, &scratch, &["--detailed", policies.to_str().unwrap()]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Decided by rule: when type"), "{}", stdout);
+    assert!(stdout.contains("Decided by rule: 1"), "{}", stdout);
+    assert!(stdout.contains("Marked as done: 1"), "{}", stdout);
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/notifications/threads/101")
+            .len()
+    );
+    // Only the issue reaches the model: one policy request and one summary.
+    assert_eq!(2, mock.requests_to("POST", "/v1/chat/completions").len());
+    assert!(mock
+        .requests_to("GET", "/repos/rescrv/ghai/issues/7/comments")
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runs_can_be_undone() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    mock.on("DELETE", "/notifications/threads/101/subscription")
+        .status(204);
+    mock.on("PUT", "/notifications/threads/101/subscription")
+        .body(r#"{"subscribed": true, "ignored": false}"#);
+    mock.on("PUT", "/notifications/threads/102/subscription")
+        .body(r#"{"subscribed": true, "ignored": false}"#);
+    mock.on("PUT", "/user/starred/rescrv/ghai").status(204);
+    mock.on("DELETE", "/user/starred/rescrv/ghai").status(204);
+    mock.on("GET", "/repos/rescrv/ghai/issues/7/labels")
+        .body(r#"["bug"]"#);
+    mock.on("POST", "/repos/rescrv/ghai/issues/7/labels")
+        .body("[]");
+    mock.on("DELETE", "/repos/rescrv/ghai/issues/7/labels/triaged")
+        .body("[]");
+    let scratch = scratch("undo");
+    seed_decisions(&mock, &scratch, |thread| Decision {
+        action: Some(if thread.id == "101" {
+            DecisionAction::Unsubscribe
+        } else {
+            DecisionAction::MarkRead
+        }),
+        priority: None,
+        label: vec!["triaged".to_string(), "bug".to_string()],
+        star_repo: thread.id == "101",
+        apply_label: thread.id == "101",
+    })
+    .await;
+
+    let output = triage(&mock, &scratch, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let run_id = stdout
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("↩ To undo this run: ghai-process-notifications --undo ")
+        })
+        .unwrap_or_else(|| panic!("{}", stdout))
+        .to_string();
+    // Only the label the issue did not already have is added.
+    let posted = mock.requests_to("POST", "/repos/rescrv/ghai/issues/7/labels");
+    assert_eq!(r#"{"labels":["triaged"]}"#, posted[0].body);
+
+    let output = process_notifications(&mock, &scratch, &["--undo"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(
+        stdout.contains(&format!("Undoing run {} (4 changes)", run_id)),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("PUT", "/notifications/threads/101/subscription")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/user/starred/rescrv/ghai")
+            .len()
+    );
+    assert_eq!(
+        1,
+        mock.requests_to("DELETE", "/repos/rescrv/ghai/issues/7/labels/triaged")
+            .len()
+    );
+    assert!(mock
+        .requests_to("DELETE", "/repos/rescrv/ghai/issues/7/labels/bug")
+        .is_empty());
+    // Reads cannot be undone, so the threads are listed instead.
+    assert!(
+        stdout.contains("https://github.com/rescrv/ghai/pull/7"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("https://github.com/rescrv/ghai/issues/8"),
+        "{}",
+        stdout
+    );
+
+    let output = process_notifications(&mock, &scratch, &["--undo"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Nothing to undo"), "{}", stdout);
+    let output = process_notifications(&mock, &scratch, &["--undo", &run_id]);
+    assert!(!output.status.success());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unread_threads_are_queued_for_review() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+    let scratch = scratch("queue");
+    seed_decisions(&mock, &scratch, |thread| Decision {
+        action: Some(DecisionAction::MarkUnread),
+        priority: Some(if thread.id == "101" {
+            Priority::High
+        } else {
+            Priority::Low
+        }),
+        label: if thread.id == "101" {
+            vec!["review".to_string()]
+        } else {
+            vec![]
+        },
+        star_repo: false,
+        apply_label: false,
+    })
+    .await;
+    let queue_file = scratch.join("queue.md");
+
+    let output = triage(
+        &mock,
+        &scratch,
+        &[
+            "--queue-format",
+            "markdown",
+            "--queue-file",
+            queue_file.to_str().unwrap(),
+        ],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Wrote 2 notifications to review"),
+        "{}",
+        stdout
+    );
+    let markdown = std::fs::read_to_string(&queue_file).unwrap();
+    assert_eq!(
+        "# Review queue\n\
+         \n## review\n\n\
+         - **high** [Add pagination to fetchers](https://github.com/rescrv/ghai/pull/7) (rescrv/ghai, updated 2024-05-01)\n  Summary of Add pagination to fetchers\n\
+         \n## Unlabeled\n\n\
+         - **low** [Notifications past 50 are dropped](https://github.com/rescrv/ghai/issues/8) (rescrv/ghai, updated 2024-05-01)\n  Summary of Notifications past 50 are dropped\n",
+        markdown
+    );
+    assert!(mock
+        .requests_to("PATCH", "/notifications/threads/101")
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_queue_formats_are_rejected() {
+    let mock = MockGitHub::start().await;
+
+    let output = process_notifications(
+        &mock,
+        &scratch("unknown-queue-format"),
+        &["--queue-format", "yaml"],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown --queue-format 'yaml'"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watch_processes_changes_until_terminated() {
+    let mock = MockGitHub::start().await;
+    // The first poll sees two threads, the second sees the same two unchanged, and every poll
+    // after that is told nothing has changed.
+    mock.on("GET", "/notifications")
+        .header("Last-Modified", "Wed, 01 May 2024 12:00:00 GMT")
+        .header("X-Poll-Interval", "0")
+        .fixture("notifications_page1.json")
+        .times(2);
+    mock.on("GET", "/notifications")
+        .status(304)
+        .header("X-Poll-Interval", "0");
+    serve_triage(&mock);
+    let scratch = scratch("watch");
+    let llm_url = format!("{}/v1", mock.url());
+
+    let child = command(
+        &mock,
+        &scratch,
+        &triage_args(&llm_url, &["--watch", "--no-cache"]),
+    )
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+    for _ in 0..200 {
+        if mock.requests_to("GET", "/notifications").len() >= 4 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+    let output = child.wait_with_output().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Stopped watching"), "{}", stdout);
+    assert!(stdout.contains("Marked as read: 2"), "{}", stdout);
+    // Unchanged threads are not decided again even without the cache.
+    assert_eq!(4, mock.requests_to("POST", "/v1/chat/completions").len());
+    let polls = mock.requests_to("GET", "/notifications");
+    assert_eq!(None, polls[0].header("If-Modified-Since"));
+    assert_eq!(
+        Some("Wed, 01 May 2024 12:00:00 GMT"),
+        polls[2].header("If-Modified-Since")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watch_requires_unattended_mode() {
+    let mock = MockGitHub::start().await;
+
+    let output = process_notifications(&mock, &scratch("watch-requires"), &["--watch"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--watch flag requires"), "{}", stderr);
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn context_is_fit_into_the_token_budget() {
+    let mock = MockGitHub::start().await;
+    serve_triage(&mock);
+
+    let output = triage(
+        &mock,
+        &scratch("context-tokens"),
+        &["--context-tokens", "550"],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}\n{}", stdout, stderr);
+    let chats = mock.requests_to("POST", "/v1/chat/completions");
+    let policy: serde_json::Value = serde_json::from_str(&chats[2].body).unwrap();
+    let prompt = policy["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|message| message["content"].as_str())
+        .collect::<String>();
+    // New reviews and comments are kept before older material; what no longer fits collapses into
+    // a count of what was left out.
+    assert!(
+        prompt.contains("<state>CHANGES_REQUESTED</state>"),
+        "{}",
+        prompt
+    );
+    assert!(prompt.contains("ready for another look"), "{}", prompt);
+    assert!(
+        prompt.contains("<review_comments truncated=\"true\" omitted=\"1\">"),
+        "{}",
+        prompt
+    );
+    assert!(!prompt.contains("spin forever"), "{}", prompt);
+}
@@ -0,0 +1,304 @@
+//! A local stand-in for the GitHub REST API that serves recorded fixtures.
+//!
+//! Routes are matched in the order they were registered.  Fixture bodies are read from
+//! `tests/fixtures` and every `{{base_url}}` in them is replaced with the server's own URL, so
+//! links between resources (e.g. a notification's `subject.url`) lead back to the mock.  Every
+//! request is recorded so tests can assert on paths, query parameters, headers, and bodies.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Token the mock expects in the `Authorization` header
+pub const TOKEN: &str = "ghp_mocktoken";
+
+/////////////////////////////////////////// RecordedRequest ////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// The first value of query parameter `key`
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The value of header `name`, which is matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+/////////////////////////////////////////////// Route //////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+struct Route {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    body_contains: Vec<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    remaining: Option<usize>,
+}
+
+impl Route {
+    fn matches(&self, req: &RecordedRequest) -> bool {
+        self.remaining != Some(0)
+            && self.method == req.method
+            && self.path == req.path
+            && self
+                .query
+                .iter()
+                .all(|(k, v)| req.param(k) == Some(v.as_str()))
+            && self.body_contains.iter().all(|s| req.body.contains(s))
+    }
+}
+
+/// Configures a route; it is installed when dropped
+pub struct RouteBuilder<'a> {
+    mock: &'a MockGitHub,
+    route: Option<Route>,
+}
+
+impl RouteBuilder<'_> {
+    /// Only match requests carrying this query parameter
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.route_mut()
+            .query
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Only match requests whose body contains `needle`
+    pub fn body_contains(mut self, needle: &str) -> Self {
+        self.route_mut().body_contains.push(needle.to_string());
+        self
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.route_mut().status = status;
+        self
+    }
+
+    /// Add a response header; `{{base_url}}` in the value is replaced with the mock's URL
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let value = value.replace("{{base_url}}", &self.mock.url());
+        self.route_mut().headers.push((name.to_string(), value));
+        self
+    }
+
+    /// Respond with the contents of `tests/fixtures/<name>`
+    pub fn fixture(mut self, name: &str) -> Self {
+        self.route_mut().body = self.mock.fixture(name);
+        self
+    }
+
+    /// Respond with a literal body
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.route_mut().body = body.into();
+        self
+    }
+
+    /// Stop matching after `n` requests
+    pub fn times(mut self, n: usize) -> Self {
+        self.route_mut().remaining = Some(n);
+        self
+    }
+
+    fn route_mut(&mut self) -> &mut Route {
+        self.route.as_mut().unwrap()
+    }
+}
+
+impl Drop for RouteBuilder<'_> {
+    fn drop(&mut self) {
+        if let Some(route) = self.route.take() {
+            self.mock.state.lock().unwrap().routes.push(route);
+        }
+    }
+}
+
+///////////////////////////////////////////// MockGitHub ///////////////////////////////////////////
+
+#[derive(Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<RecordedRequest>,
+}
+
+pub struct MockGitHub {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockGitHub {
+    /// Start serving on an ephemeral localhost port
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, Arc::clone(&server_state)));
+            }
+        });
+        Self { addr, state }
+    }
+
+    /// The base URL to point a client or `GHAI_API_URL` at
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Register a route answering `method` requests to `path` with a 200 and an empty body
+    pub fn on(&self, method: &str, path: &str) -> RouteBuilder<'_> {
+        RouteBuilder {
+            mock: self,
+            route: Some(Route {
+                method: method.to_string(),
+                path: path.to_string(),
+                query: Vec::new(),
+                body_contains: Vec::new(),
+                status: 200,
+                headers: Vec::new(),
+                body: String::new(),
+                remaining: None,
+            }),
+        }
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The requests received for `method` and `path`
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.method == method && r.path == path)
+            .collect()
+    }
+
+    /// Read `tests/fixtures/<name>`, pointing its URLs at this server
+    pub fn fixture(&self, name: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("could not read fixture {}: {}", path, e))
+            .replace("{{base_url}}", &self.url())
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(request) = read_request(&mut reader).await {
+        let route = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(request.clone());
+            let route = state.routes.iter_mut().find(|r| r.matches(&request));
+            route.map(|route| {
+                if let Some(remaining) = route.remaining.as_mut() {
+                    *remaining -= 1;
+                }
+                route.clone()
+            })
+        };
+        let (status, headers, body) = match route {
+            Some(route) => (route.status, route.headers, route.body),
+            None => (
+                404,
+                Vec::new(),
+                format!(
+                    r#"{{"message": "Not Found: {} {}", "documentation_url": "https://docs.github.com/rest"}}"#,
+                    request.method, request.path
+                ),
+            ),
+        };
+        let mut response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            status,
+            body.len()
+        );
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        response.push_str(&body);
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn read_request<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Option<RecordedRequest> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.ok()?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (target, Vec::new()),
+    };
+    Some(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| s.to_string())
+            };
+            (decode(k), decode(v))
+        })
+        .collect()
+}